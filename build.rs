@@ -0,0 +1,14 @@
+//! Tells the recipe cache which moonlighter it's built with, so results from another one are dropped.
+
+fn main() {
+	println!("cargo::rerun-if-changed=Cargo.lock");
+	let lock = std::fs::read_to_string("Cargo.lock").unwrap_or_default();
+	println!("cargo::rustc-env=MOONLIGHTER_REV={}", moonlighter_rev(&lock).unwrap_or("unknown"));
+}
+
+/// The commit moonlighter is locked to, or its version if it doesn't come from git.
+fn moonlighter_rev(lock: &str) -> Option<&str> {
+	let package = lock.split("[[package]]").find(|package| package.lines().any(|line| line.trim() == r#"name = "moonlighter""#))?;
+	let field = |name: &str| package.lines().find_map(|line| line.trim().strip_prefix(name)?.strip_prefix(" = \"")?.strip_suffix('"'));
+	field("source").and_then(|source| source.rsplit_once('#')).map_or_else(|| field("version"), |(_, rev)| Some(rev))
+}
@@ -0,0 +1,332 @@
+//! Picking one of the 138 affinities: type to filter, arrow keys and Enter to choose, grouped by
+//! kind of skill, with favourites and recent picks on top.
+
+use std::collections::BTreeSet;
+
+use crate::variants;
+
+/// How many recent picks to keep.
+const RECENT: usize = 8;
+
+/// What the pickers remember between uses, by affinity name.
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Picks {
+	/// Most recent first.
+	pub recent: Vec<String>,
+	pub favourites: BTreeSet<String>,
+}
+
+impl Picks {
+	fn remember(&mut self, name: &str) {
+		self.recent.retain(|recent| recent != name);
+		self.recent.insert(0, name.to_owned());
+		self.recent.truncate(RECENT);
+	}
+}
+
+/// Affinities by kind of skill. The ones in none of these lists go under Other.
+const CHARACTERISTICS: &[&str] = &[
+	"Body",
+	"BodyControl",
+	"BodyStamina",
+	"BodyStrength",
+	"Mind",
+	"MindLogic",
+	"MindSpeed",
+	"Soul",
+	"SoulDepth",
+	"SoulStrength",
+];
+const FIGHTING: &[&str] = &[
+	"AggressiveFighting",
+	"Archery",
+	"Axes",
+	"Catapults",
+	"Clubs",
+	"DefensiveFighting",
+	"Fighting",
+	"Halberd",
+	"Hammers",
+	"HugeAxe",
+	"HugeClub",
+	"Knives",
+	"LargeAxe",
+	"LargeMaul",
+	"LargeMetalShield",
+	"LargeWoodenShield",
+	"LongBow",
+	"LongSpear",
+	"Longsword",
+	"Mauls",
+	"MediumMaul",
+	"MediumMetalShield",
+	"MediumWoodenShield",
+	"NormalFighting",
+	"Polearms",
+	"ReflexBow",
+	"ShieldBashing",
+	"Shields",
+	"ShortBow",
+	"Shortsword",
+	"SmallAxe",
+	"SmallMaul",
+	"SmallMetalShield",
+	"SmallWoodenShield",
+	"Staff",
+	"Swords",
+	"Taunting",
+	"Trebuchets",
+	"TwoHandedSword",
+	"WarMachines",
+	"Warhammer",
+	"WeaponlessFighting",
+];
+const CRAFTING: &[&str] = &[
+	"Alchemy",
+	"ArmourSmithing",
+	"Baking",
+	"Beverages",
+	"Blacksmithing",
+	"BladesSmithing",
+	"Bowyery",
+	"Carpentry",
+	"ChainArmourSmithing",
+	"ClothTailoring",
+	"CoalMaking",
+	"Cooking",
+	"DairyFoodMaking",
+	"FineCarpentry",
+	"Firemaking",
+	"Fletching",
+	"HotFoodCooking",
+	"JewelrySmithing",
+	"Leatherworking",
+	"Locksmithing",
+	"Masonry",
+	"Metallurgy",
+	"Milling",
+	"NaturalSubstances",
+	"Papyrusmaking",
+	"PlateArmourSmithing",
+	"Pottery",
+	"Repairing",
+	"Restoration",
+	"Ropemaking",
+	"ShieldSmithing",
+	"ShipBuilding",
+	"Smithing",
+	"StoneCutting",
+	"Tailoring",
+	"Thatching",
+	"ToyMaking",
+	"Toys",
+	"WeaponHeadsSmithing",
+	"WeaponSmithing",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Category {
+	Characteristics,
+	Fighting,
+	Crafting,
+	Other,
+}
+
+impl Category {
+	const ALL: [Self; 4] = [Self::Characteristics, Self::Fighting, Self::Crafting, Self::Other];
+
+	fn of(name: &str) -> Self {
+		if CHARACTERISTICS.contains(&name) {
+			Self::Characteristics
+		} else if FIGHTING.contains(&name) {
+			Self::Fighting
+		} else if CRAFTING.contains(&name) {
+			Self::Crafting
+		} else {
+			Self::Other
+		}
+	}
+
+	fn heading(self) -> &'static str {
+		match self {
+			Self::Characteristics => "Characteristics",
+			Self::Fighting => "Fighting",
+			Self::Crafting => "Crafting",
+			Self::Other => "Other",
+		}
+	}
+}
+
+/// The open popup's filter text and highlighted row, kept in egui's memory.
+#[derive(Clone, Default)]
+struct PopupState {
+	filter: String,
+	highlighted: usize,
+}
+
+/// Shows the picker for a required affinity. Returns whether it changed.
+pub fn affinity_picker(ui: &mut egui::Ui, id_salt: impl std::hash::Hash, label: impl Into<egui::WidgetText>, selected: &mut moonlighter::Affinity, picks: &mut Picks) -> bool {
+	let picked = show(ui, id_salt, label, Some(&variants::name_of(selected)), picks);
+	if let Some(affinity) = picked {
+		*selected = affinity;
+		true
+	} else {
+		false
+	}
+}
+
+/// Shows the picker for an affinity that may not have been picked yet. Returns whether it changed.
+pub fn optional_affinity_picker(ui: &mut egui::Ui, id_salt: impl std::hash::Hash, label: impl Into<egui::WidgetText>, selected: &mut Option<moonlighter::Affinity>, picks: &mut Picks) -> bool {
+	let selected_name = selected.as_ref().map(variants::name_of);
+	let picked = show(ui, id_salt, label, selected_name.as_deref(), picks);
+	if picked.is_some() {
+		*selected = picked;
+		true
+	} else {
+		false
+	}
+}
+
+fn show(ui: &mut egui::Ui, id_salt: impl std::hash::Hash, label: impl Into<egui::WidgetText>, selected: Option<&str>, picks: &mut Picks) -> Option<moonlighter::Affinity> {
+	let id = ui.make_persistent_id(id_salt);
+	let mut picked = None;
+	let response = egui::ComboBox::new(id, label).selected_text(selected.unwrap_or("Pick a skill")).height(400.0).show_ui(ui, |ui| {
+		let mut state: PopupState = ui.data_mut(|data| data.get_temp(id)).unwrap_or_default();
+		let filter_response = ui.add(egui::TextEdit::singleline(&mut state.filter).hint_text("Type to filter"));
+		if !filter_response.has_focus() && !ui.memory(|memory| memory.focused().is_some()) {
+			filter_response.request_focus();
+		}
+		if filter_response.changed() {
+			state.highlighted = 0;
+		}
+
+		let rows = rows(&state.filter, picks);
+		let (up, down, enter) = ui.input(|input| (input.key_pressed(egui::Key::ArrowUp), input.key_pressed(egui::Key::ArrowDown), input.key_pressed(egui::Key::Enter)));
+		if down {
+			state.highlighted = (state.highlighted + 1).min(rows.len().saturating_sub(1));
+		}
+		if up {
+			state.highlighted = state.highlighted.saturating_sub(1);
+		}
+		if enter && let Some((_, name)) = rows.get(state.highlighted) {
+			picked = Some(name.clone());
+		}
+
+		let mut heading = None;
+		for (index, (section, name)) in rows.iter().enumerate() {
+			if heading != Some(section) {
+				heading = Some(section);
+				ui.label(egui::RichText::new(*section).strong());
+			}
+			ui.horizontal(|ui| {
+				let favourite = picks.favourites.contains(name);
+				if ui
+					.small_button(if favourite { "★" } else { "☆" })
+					.on_hover_text(if favourite { "Unfavourite" } else { "Favourite" })
+					.clicked()
+				{
+					if favourite {
+						picks.favourites.remove(name);
+					} else {
+						picks.favourites.insert(name.clone());
+					}
+				}
+				let row = ui.selectable_label(index == state.highlighted || selected == Some(name.as_str()), name.as_str());
+				if index == state.highlighted && (up || down) {
+					row.scroll_to_me(None);
+				}
+				if row.clicked() {
+					picked = Some(name.clone());
+				}
+			});
+		}
+		if rows.is_empty() {
+			ui.label("No affinity matches.");
+		}
+
+		if picked.is_some() {
+			ui.close();
+			ui.data_mut(|data| data.remove::<PopupState>(id));
+		} else {
+			ui.data_mut(|data| data.insert_temp(id, state));
+		}
+	});
+	if response.inner.is_none() {
+		ui.data_mut(|data| data.remove::<PopupState>(id));
+	}
+
+	let name = picked?;
+	picks.remember(&name);
+	variants::parse_affinity(&name)
+}
+
+/// The rows to list for a filter, in order, each with the heading it goes under.
+fn rows(filter: &str, picks: &Picks) -> Vec<(&'static str, String)> {
+	let filter = variants::normalize(filter);
+	let mut rows = Vec::new();
+	if filter.is_empty() {
+		rows.extend(picks.favourites.iter().map(|name| ("Favourites", name.clone())));
+		rows.extend(picks.recent.iter().map(|name| ("Recent", name.clone())));
+	}
+	for category in Category::ALL {
+		rows.extend(
+			variants::affinities()
+				.iter()
+				.filter(|name| Category::of(name) == category && variants::normalize(name).contains(&filter))
+				.map(|name| (category.heading(), (*name).to_owned())),
+		);
+	}
+	rows
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn categories_list_real_affinities() {
+		for name in CHARACTERISTICS.iter().chain(FIGHTING).chain(CRAFTING) {
+			assert!(variants::affinities().contains(name), "{name} isn't an affinity");
+		}
+	}
+
+	#[test]
+	fn rows_put_favourites_and_recent_picks_first() {
+		let mut picks = Picks::default();
+		picks.favourites.insert("Carpentry".to_owned());
+		for name in ["Body", "Archery", "Body"] {
+			picks.remember(name);
+		}
+		assert_eq!(picks.recent, ["Body", "Archery"]);
+
+		let rows = rows("", &picks);
+		let first: Vec<_> = rows.iter().take(4).map(|(section, name)| (*section, name.as_str())).collect();
+		assert_eq!(first, [("Favourites", "Carpentry"), ("Recent", "Body"), ("Recent", "Archery"), ("Characteristics", "Body")]);
+		assert_eq!(rows.len(), 3 + variants::affinities().len());
+		let headings: Vec<_> = Category::ALL.iter().map(|category| category.heading()).collect();
+		let order: Vec<_> = rows.iter().skip(3).filter_map(|(section, _)| headings.iter().position(|heading| heading == section)).collect();
+		assert!(order.is_sorted(), "categories out of order");
+		assert_eq!(order.len(), variants::affinities().len());
+	}
+
+	#[test]
+	fn rows_filter_loosely_without_picks() {
+		let mut picks = Picks::default();
+		picks.favourites.insert("Longsword".to_owned());
+		picks.remember("Longsword");
+		let rows = rows("long S-W", &picks);
+		assert_eq!(rows, [("Fighting", "Longsword".to_owned())]);
+		assert!(super::rows("no such skill", &picks).is_empty());
+	}
+
+	#[test]
+	fn recent_picks_are_capped() {
+		let mut picks = Picks::default();
+		for name in variants::affinities().iter().take(RECENT + 2) {
+			picks.remember(name);
+		}
+		assert_eq!(picks.recent.len(), RECENT);
+		assert_eq!(picks.recent.first().map(String::as_str), variants::affinities().get(RECENT + 1).copied());
+	}
+}
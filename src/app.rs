@@ -1,42 +1,210 @@
+use crate::{affinity_picker, batch, cache, calibration, cookbook, core, export, history, inventory, link, profiles, share, variants, worker};
+
+/// A change to the settings suggested by a [`worker::Diagnosis`].
+enum Relaxation {
+	MaxFillers(u64),
+	Vegetables(u64),
+	OtherCereals,
+	ExtraOffset(u64),
+}
+
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 
-#[derive(PartialEq, Eq)]
 enum GeneratingState {
 	Idle,
-	GenerateClicked,
-	Generating,
+	Generating {
+		job: worker::Job,
+		/// Kept to cache the results under.
+		request: Box<worker::SearchRequest>,
+		progress: Box<worker::ProgressTracker>,
+		/// Shown again if the search is cancelled.
+		previous_recipe: Option<moonlighter::Recipe>,
+		previous_recipe_optimal: bool,
+		previous_recipe_origin: Option<Box<Origin>>,
+		previous_checklist: Vec<core::ChecklistItem>,
+	},
+	Cancelled,
+}
+
+/// The settings and player number a recipe was made for.
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+struct Origin {
+	options: core::Options,
+	player_number: u64,
+}
+
+impl Origin {
+	fn of(request: &worker::SearchRequest) -> Self {
+		Self {
+			options: request.options.clone(),
+			player_number: request.player_number,
+		}
+	}
+
+	/// For a recipe found with other settings than the requested ones.
+	fn with_run(mut self, run: &worker::Run) -> Self {
+		run.apply(&mut self.options);
+		self
+	}
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
 pub struct TemplateApp {
-	full_cereals: bool,
-	complex_processing: bool,
-	custom_offset: u64,
-	vegetables: u64,
-	max_fillers: u64,
-	checkbox_states: [bool; 18],
+	/// Saved at the top level before; see [`UngroupedOptions`].
+	options: core::Options,
+	/// How many recipes to list; more than one also searches nearby settings.
+	alternatives: u64,
+	/// The steps of `recipe`, with what's been done. Carried over to a new recipe with the same
+	/// steps, and put back if a search is cancelled.
+	checklist: Vec<core::ChecklistItem>,
+	/// The instruction the guided brewing view is on, if it's open.
+	brewing_step: Option<usize>,
 	#[serde(skip)]
 	generating_state: GeneratingState,
+	/// A cancelled search that hasn't stopped yet. The next search waits for it.
+	#[serde(skip)]
+	stopping: Option<worker::Job>,
 
-	affinity: moonlighter::Affinity,
 	bear_meal_affinity: moonlighter::Affinity,
 	recipe: Option<moonlighter::Recipe>,
+	/// Whether `recipe` is the solver's answer for its settings, rather than a candidate with fewer
+	/// vegetables picked up while searching.
+	recipe_optimal: bool,
+	/// What `recipe` was made for. The settings may have been changed since.
+	recipe_origin: Option<Origin>,
+	/// Recipes found by the last search, best first, when more than one was asked for.
+	alternative_recipes: Vec<worker::Alternative>,
+	/// The options of the search `alternative_recipes` came from.
+	alternatives_options: core::Options,
+	/// Which of `alternative_recipes` is `recipe`.
+	shown_alternative: usize,
+	/// Why the last search found nothing for its settings, if it didn't.
+	diagnosis: Option<worker::Diagnosis>,
+	inventory: inventory::Inventory,
+	exclusions: inventory::Exclusions,
+	#[serde(skip)]
+	inventory_paste: String,
+	/// Pasted lines that couldn't be read.
+	#[serde(skip)]
+	inventory_unread: Vec<String>,
+	#[serde(skip)]
+	calibration_wizard: Option<calibration::Wizard>,
+	/// Saved options, for every character.
+	presets: Vec<core::Preset>,
+	/// Name for the next preset saved.
+	#[serde(skip)]
+	preset_name: String,
+	/// Affinities queued to solve one after another, with what was found.
+	batch: batch::Batch,
+	/// The affinity to add to the batch next.
+	#[serde(skip)]
+	batch_affinity: Option<moonlighter::Affinity>,
+	cookbooks: cookbook::Cookbooks,
+	#[serde(skip)]
+	cookbook_open: bool,
+	#[serde(skip)]
+	cookbook_filter: String,
+	/// Recipes generated before, for every character.
+	history: history::History,
+	/// A recipe exported as JSON or CSV, pasted in to import.
+	#[serde(skip)]
+	import_text: String,
+	/// A share code pasted in to open.
+	#[serde(skip)]
+	share_code: String,
+	/// Why the last import or share code failed.
+	#[serde(skip)]
+	import_message: Option<String>,
+	/// A warning that the recipe opened from a link, file or share code was made for another player number.
+	#[serde(skip)]
+	notice: Option<String>,
+	/// Stored under its own key, so it can be dropped without touching the rest.
+	#[serde(skip)]
+	cache: cache::Cache,
+	/// Whether `recipe` and the rest came from the cache rather than a search just now.
+	#[serde(skip)]
+	recipe_cached: bool,
+	/// Recent and favourite affinities, shared by every affinity picker.
+	affinity_picks: affinity_picker::Picks,
+	/// Test meals and brews the player saw the affinity of, to check the calibration against.
+	observations: Vec<core::Observation>,
+	/// Stored under their own key; see [`profiles::Profiles::current`] for how they relate to the
+	/// fields above.
+	#[serde(skip)]
+	profiles: profiles::Profiles,
+	/// The fragment last put in the address bar, so it's only written when it changes.
+	#[cfg(target_arch = "wasm32")]
+	#[serde(skip)]
+	link_fragment: String,
 }
 
 impl Default for TemplateApp {
 	fn default() -> Self {
 		Self {
+			options: core::Options::default(),
 			bear_meal_affinity: moonlighter::Affinity::AggressiveFighting,
-			affinity: moonlighter::Affinity::AggressiveFighting,
-			full_cereals: true,
-			complex_processing: false,
 			recipe: None,
-			custom_offset: 0,
-			vegetables: 12,
-			max_fillers: 80,
-			checkbox_states: [false; 18],
+			recipe_optimal: true,
+			recipe_origin: None,
+			alternatives: 1,
+			alternative_recipes: Vec::new(),
+			alternatives_options: core::Options::default(),
+			shown_alternative: 0,
+			diagnosis: None,
+			inventory: inventory::Inventory::default(),
+			exclusions: inventory::Exclusions::default(),
+			inventory_paste: String::new(),
+			inventory_unread: Vec::new(),
+			calibration_wizard: None,
+			affinity_picks: affinity_picker::Picks::default(),
+			presets: Vec::new(),
+			batch: batch::Batch::default(),
+			batch_affinity: None,
+			cookbooks: cookbook::Cookbooks::default(),
+			cookbook_open: false,
+			cookbook_filter: String::new(),
+			history: history::History::default(),
+			import_text: String::new(),
+			share_code: String::new(),
+			import_message: None,
+			notice: None,
+			cache: cache::Cache::default(),
+			recipe_cached: false,
+			preset_name: String::new(),
+			observations: Vec::new(),
+			profiles: profiles::Profiles::default(),
+			checklist: Vec::new(),
+			brewing_step: None,
 			generating_state: GeneratingState::Idle,
+			stopping: None,
+			#[cfg(target_arch = "wasm32")]
+			link_fragment: String::new(),
+		}
+	}
+}
+
+/// The options as saved before they were grouped into [`core::Options`], next to the rest of the
+/// app state. State saved since doesn't have them, so doesn't read as this.
+#[derive(serde::Deserialize)]
+struct UngroupedOptions {
+	affinity: moonlighter::Affinity,
+	vegetables: u64,
+	max_fillers: u64,
+	complex_processing: bool,
+	full_cereals: bool,
+	custom_offset: u64,
+}
+
+impl From<UngroupedOptions> for core::Options {
+	fn from(options: UngroupedOptions) -> Self {
+		Self {
+			affinity: options.affinity,
+			vegetables: options.vegetables,
+			max_fillers: options.max_fillers,
+			complex_processing: options.complex_processing,
+			full_cereals: options.full_cereals,
+			custom_offset: options.custom_offset,
 		}
 	}
 }
@@ -49,15 +217,1006 @@ impl TemplateApp {
 
 		// Load previous app state (if any).
 		// Note that you must enable the `persistence` feature for this to work.
-		if let Some(storage) = cc.storage {
-			eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default()
-		} else {
-			Default::default()
+		Self::load(cc.storage)
+	}
+
+	fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+		let mut app: Self = storage.and_then(|storage| eframe::get_value(storage, eframe::APP_KEY)).unwrap_or_default();
+		if let Some(ungrouped) = storage.and_then(|storage| eframe::get_value::<UngroupedOptions>(storage, eframe::APP_KEY)) {
+			app.options = ungrouped.into();
+		}
+		app.profiles = storage.and_then(|storage| eframe::get_value(storage, profiles::STORAGE_KEY)).unwrap_or_default();
+		if app.profiles.list.is_empty() {
+			app.profiles.list.push(profiles::Profile {
+				name: profiles::Profile::default().name,
+				..app.profile()
+			});
+		}
+		app.profiles.current = app.profiles.current.min(app.profiles.list.len().saturating_sub(1));
+		app.batch.restore();
+		app.cookbooks.restore();
+		app.cache = cache::Cache::load(storage);
+		if let Some(recipe) = &app.recipe {
+			app.checklist = core::checklist(recipe, &app.checklist);
+			// Saved before the origin was; the settings are the best guess.
+			if app.recipe_origin.is_none() {
+				app.recipe_origin = Some(Origin {
+					options: app.options.clone(),
+					player_number: app.player_number(),
+				});
+			}
+		}
+		app
+	}
+
+	/// The current character's settings, as they are now.
+	fn profile(&self) -> profiles::Profile {
+		profiles::Profile {
+			name: self.profiles.current_name().to_owned(),
+			bear_meal_affinity: self.bear_meal_affinity.clone(),
+			observations: self.observations.clone(),
+			options: self.options.clone(),
+			inventory: self.inventory.clone(),
+			exclusions: self.exclusions.clone(),
+		}
+	}
+
+	fn load_profile(&mut self, profile: profiles::Profile) {
+		self.bear_meal_affinity = profile.bear_meal_affinity;
+		self.observations = profile.observations;
+		self.options = profile.options;
+		self.inventory = profile.inventory;
+		self.exclusions = profile.exclusions;
+		self.inventory_unread.clear();
+	}
+
+	/// Writes the current character's settings back into its profile.
+	fn store_profile(&mut self) {
+		let profile = self.profile();
+		if let Some(stored) = self.profiles.list.get_mut(self.profiles.current) {
+			*stored = profile;
+		}
+	}
+
+	fn switch_profile(&mut self, index: usize) {
+		self.store_profile();
+		if let Some(profile) = self.profiles.list.get(index).cloned() {
+			self.profiles.current = index;
+			self.load_profile(profile);
 		}
 	}
 
+	fn profile_menu(&mut self, ui: &mut egui::Ui) {
+		ui.menu_button(format!("Character: {}", self.profiles.current_name()), |ui| {
+			let mut switch_to = None;
+			for (index, profile) in self.profiles.list.iter().enumerate() {
+				if ui.radio(index == self.profiles.current, &profile.name).clicked() {
+					switch_to = Some(index);
+				}
+			}
+			ui.separator();
+			if let Some(profile) = self.profiles.list.get_mut(self.profiles.current) {
+				ui.horizontal(|ui| {
+					ui.label("Name:");
+					ui.text_edit_singleline(&mut profile.name);
+				});
+			}
+			if ui.button("New character").clicked() {
+				switch_to = Some(self.profiles.list.len());
+				self.profiles.list.push(profiles::Profile {
+					name: self.profiles.unused_name(),
+					..profiles::Profile::default()
+				});
+			}
+			if self.profiles.list.len() > 1 && ui.button("Delete this character").clicked() {
+				self.profiles.list.remove(self.profiles.current);
+				self.profiles.current = self.profiles.current.min(self.profiles.list.len() - 1);
+				if let Some(profile) = self.profiles.list.get(self.profiles.current).cloned() {
+					self.load_profile(profile);
+				}
+				ui.close();
+			}
+			if let Some(index) = switch_to {
+				self.switch_profile(index);
+				ui.close();
+			}
+		});
+	}
+
 	pub fn player_number(&self) -> u64 {
-		(138 + 57 + self.bear_meal_affinity.offset() - moonlighter::Affinity::CoalMaking.offset()) % 138
+		core::player_number(&self.bear_meal_affinity)
+	}
+
+	/// Shows the cached results for the current settings if there are any, and searches otherwise.
+	fn generate(&mut self, ctx: &egui::Context) {
+		let request = self.search_request();
+		let Some(results) = self.cache.get(&request) else {
+			self.start_generating(ctx);
+			return;
+		};
+		self.recipe_optimal = results.best.is_some();
+		(self.recipe, self.recipe_origin) = match results.best {
+			Some(best) => (Some(best), Some(Origin::of(&request))),
+			None => results
+				.alternatives
+				.first()
+				.map(|alternative| (alternative.recipe.clone(), Origin::of(&request).with_run(&alternative.run)))
+				.unzip(),
+		};
+		if let Some(recipe) = &self.recipe {
+			self.checklist = core::checklist(recipe, &self.checklist);
+		}
+		self.alternative_recipes = results.alternatives;
+		self.alternatives_options = self.options.clone();
+		self.shown_alternative = 0;
+		self.diagnosis = results.diagnosis;
+		self.generating_state = GeneratingState::Idle;
+		self.recipe_cached = true;
+		self.record_history(&request);
+	}
+
+	fn start_generating(&mut self, ctx: &egui::Context) {
+		let request = self.search_request();
+		self.generating_state = GeneratingState::Generating {
+			job: worker::Job::spawn(ctx, &request, self.stopping.take()),
+			progress: Box::new(worker::ProgressTracker::new(ctx.input(|i| i.time), request.runs())),
+			request: Box::new(request),
+			previous_recipe: self.recipe.take(),
+			previous_recipe_optimal: self.recipe_optimal,
+			previous_recipe_origin: self.recipe_origin.take().map(Box::new),
+			previous_checklist: self.checklist.clone(),
+		};
+		self.diagnosis = None;
+		self.recipe_cached = false;
+	}
+
+	/// Takes in whatever the running search has reported since the last frame.
+	fn poll_generating(&mut self, now: f64) {
+		let GeneratingState::Generating { job, progress, request, .. } = &mut self.generating_state else {
+			return;
+		};
+		while let Some(message) = job.poll() {
+			match message {
+				worker::Message::Progress(update) => progress.record(update, now),
+				worker::Message::Candidate(recipe) => {
+					let run = worker::Run {
+						vegetables: recipe.vegs.len() as u64,
+						..worker::Run::of(&request.options)
+					};
+					self.recipe_origin = Some(Origin::of(request).with_run(&run));
+					self.checklist = core::checklist(&recipe, &self.checklist);
+					self.recipe = Some(recipe);
+					self.recipe_optimal = false;
+				}
+				worker::Message::Best(recipe) => {
+					// With no recipe for the full settings, the last candidate is still the best there is.
+					if let Some(recipe) = recipe {
+						self.checklist = core::checklist(&recipe, &self.checklist);
+						self.recipe = Some(recipe);
+						self.recipe_optimal = true;
+						self.recipe_origin = Some(Origin::of(request));
+					}
+				}
+				worker::Message::Diagnosis(diagnosis) => self.diagnosis = Some(diagnosis),
+				worker::Message::Finished(alternatives) => {
+					self.cache.insert(
+						request,
+						cache::Results {
+							best: self.recipe.clone().filter(|_| self.recipe_optimal),
+							alternatives: alternatives.clone(),
+							diagnosis: self.diagnosis.clone(),
+						},
+					);
+					// Nearby settings can still turn up a recipe when the requested ones gave nothing usable.
+					if !self.recipe_optimal
+						&& let Some(first) = alternatives.first()
+					{
+						self.checklist = core::checklist(&first.recipe, &self.checklist);
+						self.recipe = Some(first.recipe.clone());
+						self.recipe_origin = Some(Origin::of(request).with_run(&first.run));
+					}
+					self.alternative_recipes = alternatives;
+					self.alternatives_options = request.options.clone();
+					self.shown_alternative = 0;
+					let request = request.clone();
+					self.generating_state = GeneratingState::Idle;
+					self.record_history(&request);
+					return;
+				}
+			}
+		}
+	}
+
+	fn record_history(&mut self, request: &worker::SearchRequest) {
+		if let Some(recipe) = &self.recipe {
+			self.history.record(&request.options, request.player_number, recipe, self.recipe_optimal);
+		}
+	}
+
+	/// Shows a recipe from elsewhere than a search, with the settings it was generated for.
+	fn open_recipe(&mut self, origin: Origin, recipe: moonlighter::Recipe, optimal: bool) {
+		self.stop_generating();
+		self.options = origin.options.clone();
+		self.checklist = core::checklist(&recipe, &self.checklist);
+		self.recipe = Some(recipe);
+		self.recipe_optimal = optimal;
+		self.recipe_origin = Some(origin);
+		self.alternative_recipes.clear();
+		self.shown_alternative = 0;
+		self.diagnosis = None;
+		self.recipe_cached = false;
+	}
+
+	/// Opens the settings, and the recipe if there is one, from the web page's address.
+	pub fn open_link(&mut self, fragment: &str) {
+		let Some(link) = link::Link::from_fragment(fragment) else {
+			return;
+		};
+		let Some(recipe) = link.recipe else {
+			self.options = link.options;
+			return;
+		};
+		self.notice = link.player_number.and_then(|made_for| self.player_number_notice(made_for));
+		let origin = Origin {
+			options: link.options,
+			player_number: link.player_number.unwrap_or_else(|| self.player_number()),
+		};
+		self.open_recipe(origin, recipe, true);
+	}
+
+	/// What the recipe on screen was made for.
+	fn shown_origin(&self) -> Origin {
+		self.recipe_origin.clone().unwrap_or_else(|| Origin {
+			options: self.options.clone(),
+			player_number: self.player_number(),
+		})
+	}
+
+	/// The warning for a recipe made for `made_for`, if that isn't the current player number.
+	fn player_number_notice(&self, made_for: u64) -> Option<String> {
+		let player_number = self.player_number();
+		(made_for != player_number).then(|| format!("This recipe was made for player number {made_for}, not yours ({player_number})."))
+	}
+
+	/// What's on screen, as a link: the recipe with what it was made for, or the settings while
+	/// there's none or a search is running.
+	fn link(&self) -> link::Link {
+		let generating = matches!(self.generating_state, GeneratingState::Generating { .. });
+		match &self.recipe {
+			Some(recipe) if !generating => {
+				let origin = self.shown_origin();
+				link::Link {
+					options: origin.options,
+					player_number: Some(origin.player_number),
+					recipe: Some(recipe.clone()),
+				}
+			}
+			_ => link::Link {
+				options: self.options.clone(),
+				player_number: Some(self.player_number()),
+				recipe: None,
+			},
+		}
+	}
+
+	fn history_ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+		ui.heading("History");
+		let idle = !matches!(self.generating_state, GeneratingState::Generating { .. });
+		let now = history::now();
+		let player_number = self.player_number();
+		let mut open = None;
+		let mut rerun = None;
+		let mut remove = None;
+		for (index, entry) in self.history.entries.iter().enumerate() {
+			ui.separator();
+			ui.label(egui::RichText::new(format!("{:?}", entry.options.affinity)).strong())
+				.on_hover_text(format!("{}\nPlayer number {}", entry.options, entry.player_number));
+			ui.label(history::age(entry.timestamp, now));
+			if entry.player_number != player_number {
+				ui.colored_label(ui.visuals().warn_fg_color, format!("For player number {}", entry.player_number));
+			}
+			if !entry.optimal {
+				ui.label("From nearby settings");
+			}
+			ui.horizontal(|ui| {
+				if ui.small_button("Open").on_hover_text("Show this recipe and its settings").clicked() {
+					open = Some(index);
+				}
+				if ui
+					.add_enabled(idle, egui::Button::new("Re-run").small())
+					.on_hover_text("Search again with these settings and your current player number")
+					.clicked()
+				{
+					rerun = Some(index);
+				}
+				if ui.small_button("Delete").clicked() {
+					remove = Some(index);
+				}
+			});
+		}
+		if self.history.entries.is_empty() {
+			ui.label("Recipes you generate are listed here, to open again later.");
+		}
+		if let Some(entry) = open.and_then(|index| self.history.entries.get(index).cloned()) {
+			let origin = Origin {
+				options: entry.options,
+				player_number: entry.player_number,
+			};
+			self.open_recipe(origin, entry.recipe, entry.optimal);
+		}
+		if let Some(entry) = rerun.and_then(|index| self.history.entries.get(index)) {
+			self.options = entry.options.clone();
+			self.start_generating(ctx);
+		}
+		if let Some(index) = remove {
+			self.history.remove(index);
+		}
+	}
+
+	fn cancel_generating(&mut self) {
+		if let GeneratingState::Generating {
+			job,
+			previous_recipe,
+			previous_recipe_optimal,
+			previous_recipe_origin,
+			previous_checklist,
+			..
+		} = std::mem::replace(&mut self.generating_state, GeneratingState::Cancelled)
+		{
+			job.cancel();
+			self.stopping = Some(job);
+			self.recipe = previous_recipe;
+			self.recipe_optimal = previous_recipe_optimal;
+			self.recipe_origin = previous_recipe_origin.map(|origin| *origin);
+			self.checklist = previous_checklist;
+		}
+	}
+
+	/// Stops searching but keeps the candidate on screen.
+	fn stop_generating(&mut self) {
+		if let GeneratingState::Generating { job, .. } = std::mem::replace(&mut self.generating_state, GeneratingState::Idle) {
+			job.cancel();
+			self.stopping = Some(job);
+		}
+	}
+
+	fn search_request(&self) -> worker::SearchRequest {
+		worker::SearchRequest {
+			options: self.options.clone(),
+			player_number: self.player_number(),
+			alternatives: self.alternatives,
+			inventory: self.inventory.clone(),
+			exclusions: self.exclusions.clone(),
+			candidates: true,
+		}
+	}
+
+	fn diagnosis_ui(&mut self, ui: &mut egui::Ui) {
+		let Some(diagnosis) = &self.diagnosis else {
+			return;
+		};
+		ui.label(format!("No recipe with {} vegetables for these settings.", self.options.vegetables));
+		if diagnosis.ruled_out {
+			ui.label("The solver found one, but it needs something your inventory lacks or you've excluded.");
+		}
+		let mut any = false;
+		let mut apply = None;
+		if let Some(max_fillers) = diagnosis.max_fillers {
+			any = true;
+			ui.horizontal(|ui| {
+				ui.label(format!("Too few fillers: it works with up to {max_fillers} sugar and barley."));
+				if ui.button("Use that").clicked() {
+					apply = Some(Relaxation::MaxFillers(max_fillers));
+				}
+			});
+		}
+		if let Some(vegetables) = diagnosis.vegetables {
+			any = true;
+			ui.horizontal(|ui| {
+				ui.label(format!("Too many vegetables: it works with {vegetables}."));
+				if ui.button("Use that").clicked() {
+					apply = Some(Relaxation::Vegetables(vegetables));
+				}
+			});
+		}
+		if diagnosis.other_cereals {
+			any = true;
+			ui.horizontal(|ui| {
+				ui.label(if self.options.full_cereals {
+					"The 4 cereals don't fit: it works without them."
+				} else {
+					"It works with 4 cereals."
+				});
+				if ui.button("Use that").clicked() {
+					apply = Some(Relaxation::OtherCereals);
+				}
+			});
+		}
+		if let Some(extra_offset) = diagnosis.extra_offset {
+			any = true;
+			ui.horizontal(|ui| {
+				ui.label(format!("The offset doesn't fit: it works with {extra_offset} more, from rare or better ingredients."));
+				if ui.button("Use that").clicked() {
+					apply = Some(Relaxation::ExtraOffset(extra_offset));
+				}
+			});
+		}
+		if !any {
+			ui.label("Nothing close to these settings works either. Try allowing complex processings or another target affinity.");
+		}
+
+		match apply {
+			Some(Relaxation::MaxFillers(max_fillers)) => self.options.max_fillers = max_fillers,
+			Some(Relaxation::Vegetables(vegetables)) => self.options.vegetables = vegetables,
+			Some(Relaxation::OtherCereals) => self.options.full_cereals = !self.options.full_cereals,
+			Some(Relaxation::ExtraOffset(extra_offset)) => self.options.custom_offset += extra_offset,
+			None => return,
+		}
+		self.diagnosis = None;
+	}
+
+	fn observations_ui(&mut self, ui: &mut egui::Ui) {
+		ui.label("Record test meals, and brews that came out with the wrong affinity. Each one gives a player number, and one that disagrees with the rest was likely misread.");
+		let check = core::cross_check(&self.observations);
+		let mut remove = None;
+		egui::Grid::new("observations").striped(true).show(ui, |ui| {
+			for (index, observation) in self.observations.iter_mut().enumerate() {
+				ui.label(format!("{}.", index + 1));
+				let mut picked = Some(observation.affinity_mut().clone());
+				ui.horizontal(|ui| {
+					if let core::Observation::Brew { player_number, target, .. } = &*observation {
+						ui.label(format!("Brew for {target:?} at player number {player_number} gave"));
+					} else {
+						ui.label("Test meal gave");
+					}
+					affinity_picker::optional_affinity_picker(ui, ("observation", index), "", &mut picked, &mut self.affinity_picks);
+				});
+				if let Some(picked) = picked {
+					*observation.affinity_mut() = picked;
+				}
+				ui.label(format!("player number {}", observation.player_number()));
+				if check.contradicting.contains(&index) {
+					ui.colored_label(ui.visuals().warn_fg_color, "likely wrong");
+				} else {
+					ui.label("");
+				}
+				if ui.small_button("Remove").clicked() {
+					remove = Some(index);
+				}
+				ui.end_row();
+			}
+		});
+		if let Some(index) = remove {
+			self.observations.remove(index);
+		}
+
+		ui.horizontal(|ui| {
+			if ui.button("Add test meal").clicked() {
+				self.observations.push(core::Observation::BearMeal {
+					affinity: self.bear_meal_affinity.clone(),
+				});
+			}
+			if ui
+				.button("Add brew")
+				.on_hover_text("A brew of a recipe for the desired affinity above, made with your current player number")
+				.clicked()
+			{
+				self.observations.push(core::Observation::Brew {
+					player_number: self.player_number(),
+					target: self.options.affinity.clone(),
+					affinity: self.options.affinity.clone(),
+				});
+			}
+		});
+
+		match check.player_number {
+			Some(player_number) if check.contradicting.is_empty() && self.observations.len() > 1 => {
+				ui.label(format!("They all agree on player number {player_number}."));
+			}
+			Some(player_number) if !check.contradicting.is_empty() => {
+				ui.colored_label(
+					ui.visuals().warn_fg_color,
+					format!("Most say player number {player_number}. Check the ones marked likely wrong again, or add another test meal."),
+				);
+			}
+			None if !self.observations.is_empty() => {
+				ui.colored_label(ui.visuals().warn_fg_color, "No player number comes up more than the others. Add another test meal to settle it.");
+			}
+			_ => {}
+		}
+		if let Some(player_number) = check.player_number
+			&& player_number != self.player_number()
+		{
+			ui.horizontal(|ui| {
+				ui.colored_label(ui.visuals().warn_fg_color, format!("That's not your current player number, {}.", self.player_number()));
+				if ui.button(format!("Use {player_number}")).clicked()
+					&& let Some(affinity) = core::bear_meal_affinity(player_number)
+				{
+					self.bear_meal_affinity = affinity;
+				}
+			});
+		}
+	}
+
+	fn presets_ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+		ui.heading("Presets");
+		let idle = !matches!(self.generating_state, GeneratingState::Generating { .. });
+		let mut generate = None;
+		let mut remove = None;
+		for (index, preset) in self.presets.iter().enumerate() {
+			ui.horizontal(|ui| {
+				if ui.add_enabled(idle, egui::Button::new(&preset.name)).on_hover_text(format!("Generate {}", preset.options)).clicked() {
+					generate = Some(preset.options.clone());
+				}
+				if ui.small_button("Remove").clicked() {
+					remove = Some(index);
+				}
+			});
+		}
+		if self.presets.is_empty() {
+			ui.label("Save the settings you generate often, then generate them again with one click.");
+		}
+		if let Some(index) = remove {
+			self.presets.remove(index);
+		}
+		if let Some(options) = generate {
+			self.options = options;
+			self.generate(ctx);
+		}
+
+		ui.separator();
+		ui.add(egui::TextEdit::singleline(&mut self.preset_name).hint_text(format!("{:?}", self.options.affinity)));
+		if ui.button("Save current settings").on_hover_text(self.options.to_string()).clicked() {
+			let name = if self.preset_name.trim().is_empty() {
+				format!("{:?}", self.options.affinity)
+			} else {
+				self.preset_name.trim().to_owned()
+			};
+			let preset = core::Preset { name, options: self.options.clone() };
+			if let Some(existing) = self.presets.iter_mut().find(|existing| existing.name == preset.name) {
+				*existing = preset;
+			} else {
+				self.presets.push(preset);
+			}
+			self.preset_name.clear();
+		}
+	}
+
+	/// Queues the current settings with another affinity, for the batch.
+	fn queue_batch(&mut self, options: core::Options) {
+		self.batch.push(worker::SearchRequest {
+			options,
+			alternatives: 1,
+			candidates: false,
+			..self.search_request()
+		});
+	}
+
+	fn batch_ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+		ui.label("Queue affinities to solve one after another in the background, each with the settings above.");
+		ui.horizontal(|ui| {
+			affinity_picker::optional_affinity_picker(ui, "batch_affinity", "", &mut self.batch_affinity, &mut self.affinity_picks);
+			if ui.add_enabled(self.batch_affinity.is_some(), egui::Button::new("Add")).clicked()
+				&& let Some(affinity) = self.batch_affinity.take()
+			{
+				self.queue_batch(core::Options { affinity, ..self.options.clone() });
+			}
+			if !self.presets.is_empty() && ui.button("Add all presets").on_hover_text("With the presets' own settings").clicked() {
+				for options in self.presets.iter().map(|preset| preset.options.clone()).collect::<Vec<_>>() {
+					self.queue_batch(options);
+				}
+			}
+		});
+
+		let mut show = None;
+		let mut remove = None;
+		egui::Grid::new("batch").striped(true).show(ui, |ui| {
+			ui.strong("Affinity");
+			ui.strong("Status");
+			ui.strong("Ingredients");
+			ui.end_row();
+			for (index, entry) in self.batch.entries.iter().enumerate() {
+				ui.label(format!("{:?}", entry.request.options.affinity)).on_hover_text(entry.request.options.to_string());
+				match entry.status {
+					batch::Status::Queued => ui.label("Queued"),
+					batch::Status::Running => ui.spinner(),
+					batch::Status::Found => ui.label("Found"),
+					batch::Status::FoundNearby => ui.label("Found with other settings"),
+					batch::Status::NotFound => ui.label("No recipe"),
+					batch::Status::Cancelled => ui.label("Cancelled"),
+				};
+				if let Some(recipe) = &entry.recipe {
+					ui.label(format!("{} ({} vegetables)", worker::recipe_length(recipe), recipe.vegs.len()));
+					if ui.small_button("Show").clicked() {
+						let options = entry.recipe_options();
+						let optimal = options.is_some();
+						let origin = Origin {
+							options: options.unwrap_or_else(|| entry.request.options.clone()),
+							player_number: entry.request.player_number,
+						};
+						show = Some((origin, recipe.clone(), optimal));
+					}
+				} else {
+					ui.label("");
+					ui.label("");
+				}
+				if ui.small_button("Remove").clicked() {
+					remove = Some(index);
+				}
+				ui.end_row();
+			}
+		});
+		if let Some((origin, recipe, optimal)) = show {
+			self.open_recipe(origin, recipe, optimal);
+		}
+		// Removing the entry being searched moves on to the next one, as it would have once done.
+		if let Some(index) = remove
+			&& self.batch.remove(index)
+		{
+			self.batch.start(ctx);
+		}
+
+		ui.horizontal(|ui| {
+			if self.batch.is_running() {
+				if ui.button("Cancel").clicked() {
+					self.batch.cancel();
+				}
+			} else if ui.add_enabled(self.batch.has_queued(), egui::Button::new("Run")).clicked() {
+				self.batch.start(ctx);
+			}
+			if self.batch.is_stopping() {
+				ui.spinner();
+				ui.label("Cancelling...");
+			}
+			if self.batch.entries.iter().any(|entry| entry.status == batch::Status::Cancelled) && ui.button("Retry cancelled").clicked() {
+				self.batch.retry_cancelled();
+			}
+			if ui.add_enabled(self.batch.entries.iter().any(|entry| entry.recipe.is_some()), egui::Button::new("Copy all")).clicked() {
+				ctx.copy_text(self.batch.export_text());
+			}
+			if ui.add_enabled(!self.batch.entries.is_empty(), egui::Button::new("Clear")).clicked() {
+				self.batch.cancel();
+				self.batch.entries.clear();
+			}
+		});
+	}
+
+	fn cookbook_ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+		enum Action {
+			Build,
+			Cancel,
+			Delete,
+			/// With whether the recipe is the solver's answer for the options.
+			Show(core::Options, moonlighter::Recipe, bool),
+		}
+
+		let player_number = self.player_number();
+		ui.label(format!(
+			"The best recipe for every affinity, for player number {player_number} and the other settings in the main window."
+		));
+		let Some(cookbook) = self.cookbooks.get(player_number, &self.options) else {
+			if ui.button("Build cookbook").on_hover_text("Searches every affinity in the background. This takes a while.").clicked() {
+				self.cookbooks.build(ctx, player_number, &self.options);
+			}
+			return;
+		};
+
+		let mut action = None;
+		let (done, total) = cookbook.progress();
+		ui.horizontal(|ui| {
+			if done < total {
+				ui.add(
+					egui::ProgressBar::new(done as f32 / total.max(1) as f32)
+						.text(format!("{done} of {total} affinities"))
+						.desired_width(200.0),
+				);
+				if cookbook.batch.is_running() {
+					if ui.button("Pause").clicked() {
+						action = Some(Action::Cancel);
+					}
+				} else if ui.button("Resume").clicked() {
+					action = Some(Action::Build);
+				}
+				if cookbook.batch.is_stopping() {
+					ui.spinner();
+				}
+			}
+			if ui.button("Copy all").clicked() {
+				ctx.copy_text(cookbook.batch.export_text());
+			}
+			if ui.button("Delete").clicked() {
+				action = Some(Action::Delete);
+			}
+		});
+		ui.add(egui::TextEdit::singleline(&mut self.cookbook_filter).hint_text("Search affinities and ingredients"));
+
+		let filter = variants::normalize(&self.cookbook_filter);
+		egui::ScrollArea::vertical().show(ui, |ui| {
+			egui::Grid::new("cookbook").striped(true).show(ui, |ui| {
+				ui.strong("Affinity");
+				ui.strong("Ingredients");
+				ui.strong("Recipe");
+				ui.end_row();
+				for entry in &cookbook.batch.entries {
+					let affinity = format!("{:?}", entry.request.options.affinity);
+					let steps = entry.recipe.as_ref().map(|recipe| core::steps(recipe).iter().map(ToString::to_string).collect::<Vec<_>>().join(", "));
+					if !variants::normalize(&affinity).contains(&filter) && !steps.as_deref().is_some_and(|steps| variants::normalize(steps).contains(&filter)) {
+						continue;
+					}
+					ui.label(affinity);
+					if let (Some(recipe), Some(steps)) = (&entry.recipe, steps) {
+						ui.label(worker::recipe_length(recipe).to_string());
+						ui.add(egui::Label::new(steps).truncate());
+						if ui.small_button("Show").clicked() {
+							let options = entry.recipe_options();
+							let optimal = options.is_some();
+							action = Some(Action::Show(options.unwrap_or_else(|| entry.request.options.clone()), recipe.clone(), optimal));
+						}
+					} else {
+						ui.label("");
+						ui.label(match entry.status {
+							batch::Status::NotFound => "No recipe",
+							batch::Status::Running => "Searching...",
+							_ => "Not searched yet",
+						});
+					}
+					ui.end_row();
+				}
+			});
+		});
+
+		match action {
+			Some(Action::Build) => self.cookbooks.build(ctx, player_number, &self.options),
+			Some(Action::Cancel) => {
+				if let Some(cookbook) = self.cookbooks.get_mut(player_number, &self.options) {
+					cookbook.batch.cancel();
+				}
+			}
+			Some(Action::Delete) => self.cookbooks.remove(player_number, &self.options),
+			Some(Action::Show(options, recipe, optimal)) => self.open_recipe(Origin { options, player_number }, recipe, optimal),
+			None => {}
+		}
+	}
+
+	fn inventory_ui(&mut self, ui: &mut egui::Ui) {
+		ui.checkbox(&mut self.inventory.enabled, "Only use vegetables and processings I have");
+		ui.add_enabled_ui(self.inventory.enabled, |ui| {
+			egui::Grid::new("inventory_vegetables").num_columns(4).show(ui, |ui| {
+				for (idx, veg) in variants::vegetables().iter().enumerate() {
+					let count = self.inventory.vegetables.entry((*veg).to_owned()).or_default();
+					ui.add(egui::DragValue::new(count).range(0..=9999));
+					ui.label(*veg);
+					if idx % 2 == 1 {
+						ui.end_row();
+					}
+				}
+			});
+			ui.horizontal_wrapped(|ui| {
+				ui.label("I can do:");
+				for processing in variants::processings() {
+					let mut available = !self.inventory.missing_processings.contains(*processing);
+					if ui.checkbox(&mut available, *processing).changed() {
+						if available {
+							self.inventory.missing_processings.remove(*processing);
+						} else {
+							self.inventory.missing_processings.insert((*processing).to_owned());
+						}
+					}
+				}
+			});
+			ui.label("Or paste a list, one vegetable and count per line:");
+			ui.text_edit_multiline(&mut self.inventory_paste);
+			if ui.button("Read list").clicked() {
+				self.inventory_unread = self.inventory.paste(&self.inventory_paste);
+				if self.inventory_unread.is_empty() {
+					self.inventory_paste.clear();
+				}
+			}
+			if !self.inventory_unread.is_empty() {
+				ui.colored_label(ui.visuals().warn_fg_color, format!("Couldn't read: {}", self.inventory_unread.join("; ")));
+			}
+		});
+	}
+
+	fn export_ui(&mut self, ui: &mut egui::Ui) {
+		if let Some(recipe) = &self.recipe {
+			let origin = self.shown_origin();
+			let saved = export::SavedRecipe {
+				options: origin.options,
+				player_number: origin.player_number,
+				recipe: recipe.clone(),
+			};
+			ui.horizontal(|ui| {
+				if ui.button("Copy JSON").clicked() {
+					ui.ctx().copy_text(saved.to_json());
+				}
+				if ui.button("Copy CSV").clicked() {
+					ui.ctx().copy_text(saved.to_csv());
+				}
+			});
+		}
+		ui.label("Or paste a recipe exported as JSON or CSV:");
+		ui.text_edit_multiline(&mut self.import_text);
+		if ui.button("Import").clicked() {
+			match export::SavedRecipe::import(&self.import_text) {
+				Ok(saved) => {
+					self.import_message = None;
+					self.notice = self.player_number_notice(saved.player_number);
+					let origin = Origin {
+						options: saved.options,
+						player_number: saved.player_number,
+					};
+					self.open_recipe(origin, saved.recipe, true);
+					self.import_text.clear();
+				}
+				Err(error) => self.import_message = Some(error),
+			}
+		}
+		ui.horizontal(|ui| {
+			ui.add(egui::TextEdit::singleline(&mut self.share_code).hint_text("Share code"));
+			if ui.button("Open").clicked() {
+				self.open_share_code();
+			}
+		});
+		if let Some(message) = &self.import_message {
+			ui.colored_label(ui.visuals().warn_fg_color, message);
+		}
+	}
+
+	fn copy_share_code(&mut self, ctx: &egui::Context, recipe: &moonlighter::Recipe) {
+		let origin = self.shown_origin();
+		let shared = share::SharedRecipe {
+			affinity: origin.options.affinity,
+			player_number: origin.player_number,
+			custom_offset: origin.options.custom_offset,
+			recipe: recipe.clone(),
+		};
+		match shared.to_code() {
+			Ok(code) => ctx.copy_text(code),
+			Err(error) => self.import_message = Some(error),
+		}
+	}
+
+	fn open_share_code(&mut self) {
+		let shared = match share::SharedRecipe::from_code(&self.share_code) {
+			Ok(shared) => shared,
+			Err(error) => {
+				self.import_message = Some(error);
+				return;
+			}
+		};
+		self.import_message = None;
+		self.notice = self.player_number_notice(shared.player_number);
+		// The rest of the options aren't in the code; these are what the recipe itself shows.
+		let options = core::Options {
+			affinity: shared.affinity,
+			custom_offset: shared.custom_offset,
+			vegetables: shared.recipe.vegs.len() as u64,
+			full_cereals: shared.recipe.cereals.len() > 1,
+			complex_processing: inventory::processing_names(&shared.recipe).any(|processing| processing == "Fried" || processing == "Roasted"),
+			..self.options.clone()
+		};
+		let origin = Origin {
+			options,
+			player_number: shared.player_number,
+		};
+		self.open_recipe(origin, shared.recipe, true);
+		self.share_code.clear();
+	}
+
+	/// The guided brewing view: the recipe as numbered instructions, one at a time.
+	fn brewing_window(&mut self, ctx: &egui::Context) {
+		let (Some(current), Some(recipe)) = (self.brewing_step, &self.recipe) else {
+			self.brewing_step = None;
+			return;
+		};
+		let instructions = core::instructions(recipe);
+		let current = current.min(instructions.len().saturating_sub(1));
+		let mut open = true;
+		let mut go_to = None;
+		let mut finished = false;
+		egui::Window::new("Brewing").open(&mut open).default_width(360.0).show(ctx, |ui| {
+			for (index, instruction) in instructions.iter().enumerate() {
+				let text = egui::RichText::new(format!("{}. {}", index + 1, instruction.text));
+				let text = match index.cmp(&current) {
+					std::cmp::Ordering::Less => text.weak(),
+					std::cmp::Ordering::Equal => text.strong(),
+					std::cmp::Ordering::Greater => text,
+				};
+				let response = ui.selectable_label(index == current, text);
+				if index == current {
+					response.scroll_to_me(None);
+				}
+				if response.clicked() {
+					go_to = Some(index);
+				}
+			}
+			ui.separator();
+			ui.horizontal(|ui| {
+				if ui.add_enabled(current > 0, egui::Button::new("Previous")).clicked() {
+					go_to = Some(current - 1);
+				}
+				if current + 1 < instructions.len() {
+					if ui.button("Next").clicked() {
+						go_to = Some(current + 1);
+					}
+				} else if ui.button("Finish").clicked() {
+					go_to = Some(instructions.len());
+					finished = true;
+				}
+			});
+		});
+
+		if let Some(target) = go_to {
+			// Moving on ticks off the steps of the instructions passed, and going back unticks them.
+			let (range, done) = if target > current { (current..target, true) } else { (target..current, false) };
+			for step in instructions.get(range).into_iter().flatten().flat_map(|instruction| &instruction.steps) {
+				if let Some(item) = self.checklist.get_mut(*step) {
+					item.done = done;
+				}
+			}
+		}
+		self.brewing_step = (open && !finished).then(|| go_to.unwrap_or(current));
+	}
+
+	fn alternatives_ui(&mut self, ui: &mut egui::Ui) {
+		ui.label("Other recipes, from the same or nearby settings:");
+		let mut show = None;
+		egui::Grid::new("alternatives").striped(true).show(ui, |ui| {
+			for heading in ["", "Vegetables", "Ingredients", "Sugars", "Barleys", "Cereals", "Processings", "Settings"] {
+				ui.strong(heading);
+			}
+			ui.end_row();
+			for (idx, alternative) in self.alternative_recipes.iter().enumerate() {
+				let recipe = &alternative.recipe;
+				if ui.selectable_label(idx == self.shown_alternative, format!("#{}", idx + 1)).clicked() {
+					show = Some(idx);
+				}
+				ui.label(recipe.vegs.len().to_string());
+				ui.label(worker::recipe_length(recipe).to_string());
+				ui.label(recipe.sugars.to_string());
+				ui.label(recipe.barleys.to_string());
+				ui.label(recipe.cereals.len().to_string());
+				let processings: Vec<String> = recipe
+					.processings
+					.iter()
+					.filter(|(_, count)| *count != 0)
+					.map(|(processing, count)| format!("{count} {processing:?}"))
+					.collect();
+				ui.label(processings.join(", "));
+				let mut settings = Vec::new();
+				if alternative.run.complex_processing {
+					settings.push("complex processing");
+				}
+				if alternative.run.full_cereals {
+					settings.push("4 cereals");
+				}
+				ui.label(settings.join(", "));
+				ui.end_row();
+			}
+		});
+		if let Some(idx) = show
+			&& let Some(alternative) = self.alternative_recipes.get(idx)
+		{
+			// The settings above follow the recipe, so it's copied and exported with the right ones.
+			let mut options = self.alternatives_options.clone();
+			alternative.run.apply(&mut options);
+			self.recipe_origin = Some(Origin {
+				options: options.clone(),
+				player_number: self.recipe_origin.as_ref().map_or_else(|| self.player_number(), |origin| origin.player_number),
+			});
+			self.options = options;
+			self.checklist = core::checklist(&alternative.recipe, &self.checklist);
+			self.recipe = Some(alternative.recipe.clone());
+			self.recipe_optimal = alternative.run == worker::Run::of(&self.alternatives_options);
+			self.shown_alternative = idx;
+		}
+		if let Some(shown) = self.alternative_recipes.get(self.shown_alternative)
+			&& shown.run != worker::Run::of(&self.alternatives_options)
+			&& shown.run == worker::Run::of(&self.options)
+		{
+			ui.label("The recipe shown is from nearby settings, and the settings above have been changed to match it.");
+		}
 	}
 }
 
@@ -65,6 +1224,9 @@ impl eframe::App for TemplateApp {
 	/// Called by the framework to save state before shutdown.
 	fn save(&mut self, storage: &mut dyn eframe::Storage) {
 		eframe::set_value(storage, eframe::APP_KEY, self);
+		self.store_profile();
+		eframe::set_value(storage, profiles::STORAGE_KEY, &self.profiles);
+		eframe::set_value(storage, cache::STORAGE_KEY, &self.cache);
 	}
 
 	/// Called each time the UI needs repainting, which may be many times per second.
@@ -87,365 +1249,191 @@ impl eframe::App for TemplateApp {
 					ui.add_space(16.0);
 				}
 
+				self.profile_menu(ui);
+				ui.add_space(16.0);
+				ui.toggle_value(&mut self.cookbook_open, "Cookbook");
+				ui.add_space(16.0);
+
 				egui::widgets::global_theme_preference_buttons(ui);
 			});
 		});
 
-		egui::CentralPanel::default().show(ctx, |ui| {
-			if self.generating_state == GeneratingState::Generating {
-				self.checkbox_states = [false; 18];
-				self.recipe = moonlighter::find_recipe(&moonlighter::Options {
-					affinity: self.affinity.clone(),
-					vegs: self.vegetables,
-					max_fillers: self.max_fillers,
-					complex_processing: self.complex_processing,
-					full_cereals: self.full_cereals,
-					player_number: self.player_number(),
-					custom_offset: self.custom_offset,
-				});
-				self.generating_state = GeneratingState::Idle;
-				ctx.request_repaint();
+		let now = ctx.input(|i| i.time);
+		self.poll_generating(now);
+		if self.stopping.as_ref().is_some_and(worker::Job::has_stopped) {
+			self.stopping = None;
+		}
+		#[cfg(target_arch = "wasm32")]
+		{
+			let fragment = self.link().to_fragment();
+			if fragment != self.link_fragment {
+				link::write(&fragment);
+				self.link_fragment = fragment;
+			}
+		}
+		self.batch.poll(ctx);
+		self.cookbooks.poll(ctx);
+
+		let mut cookbook_open = self.cookbook_open;
+		egui::Window::new("Cookbook").open(&mut cookbook_open).default_width(560.0).show(ctx, |ui| self.cookbook_ui(ui, ctx));
+		self.cookbook_open = cookbook_open;
+
+		self.brewing_window(ctx);
+
+		if let Some(wizard) = &mut self.calibration_wizard {
+			match wizard.show(ctx, &mut self.affinity_picks) {
+				calibration::Outcome::Open => {}
+				calibration::Outcome::Closed => self.calibration_wizard = None,
+				calibration::Outcome::Calibrated(affinity) => {
+					self.bear_meal_affinity = affinity;
+					self.calibration_wizard = None;
+				}
 			}
+		}
+
+		egui::SidePanel::left("presets_panel").resizable(true).show(ctx, |ui| {
+			egui::ScrollArea::vertical().show(ui, |ui| self.presets_ui(ui, ctx));
+		});
+
+		egui::SidePanel::right("history_panel").resizable(true).show(ctx, |ui| {
+			egui::ScrollArea::vertical().show(ui, |ui| self.history_ui(ui, ctx));
+		});
+
+		egui::CentralPanel::default().show(ctx, |ui| {
 			// The central panel the region left after adding TopPanel's and SidePanel's
 			ui.heading("V12: 12 vegetable moonshine generator");
-			ui.label(format!("Your player number is {}", self.player_number()));
-
-			ui.add(egui::Slider::new(&mut self.custom_offset, 0..=255).text("Custom offset (+1 for each rare item used, +2 supreme, +3 fantastic)"));
-			ui.checkbox(&mut self.full_cereals, "Use 4 cereals");
-			ui.checkbox(&mut self.complex_processing, "Use complex processings options (fried and roasted)");
-
-			ui.add(egui::Slider::new(&mut self.vegetables, 1..=12).text("Unique vegetables to use"));
-			ui.add(egui::Slider::new(&mut self.max_fillers, 0..=80).text("Maximum sugar and barley to add to adjust affinity"));
-
-			let current_bear_meal_affinity = self.bear_meal_affinity.offset();
-			let selected = &mut self.bear_meal_affinity;
-
-			let combo = egui::ComboBox::from_label(format!("<- Bear + Corn + Pan + Oven gives me ({})", current_bear_meal_affinity)).selected_text(format!("{:?}", selected));
-
-			combo.show_ui(ui, |ui| {
-				ui.selectable_value(selected, moonlighter::Affinity::AggressiveFighting, "AggressiveFighting");
-				ui.selectable_value(selected, moonlighter::Affinity::Alchemy, "Alchemy");
-				ui.selectable_value(selected, moonlighter::Affinity::AnimalHusbandry, "AnimalHusbandry");
-				ui.selectable_value(selected, moonlighter::Affinity::AnimalTaming, "AnimalTaming");
-				ui.selectable_value(selected, moonlighter::Affinity::Archaeology, "Archaeology");
-				ui.selectable_value(selected, moonlighter::Affinity::Archery, "Archery");
-				ui.selectable_value(selected, moonlighter::Affinity::ArmourSmithing, "ArmourSmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::Axes, "Axes");
-				ui.selectable_value(selected, moonlighter::Affinity::Baking, "Baking");
-				ui.selectable_value(selected, moonlighter::Affinity::Beverages, "Beverages");
-				ui.selectable_value(selected, moonlighter::Affinity::Blacksmithing, "Blacksmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::BladesSmithing, "BladesSmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::Body, "Body");
-				ui.selectable_value(selected, moonlighter::Affinity::BodyControl, "BodyControl");
-				ui.selectable_value(selected, moonlighter::Affinity::BodyStamina, "BodyStamina");
-				ui.selectable_value(selected, moonlighter::Affinity::BodyStrength, "BodyStrength");
-				ui.selectable_value(selected, moonlighter::Affinity::Botanizing, "Botanizing");
-				ui.selectable_value(selected, moonlighter::Affinity::Bowyery, "Bowyery");
-				ui.selectable_value(selected, moonlighter::Affinity::Butchering, "Butchering");
-				ui.selectable_value(selected, moonlighter::Affinity::ButcheringKnife, "ButcheringKnife");
-				ui.selectable_value(selected, moonlighter::Affinity::Carpentry, "Carpentry");
-				ui.selectable_value(selected, moonlighter::Affinity::Cartography, "Cartography");
-				ui.selectable_value(selected, moonlighter::Affinity::CarvingKnife, "CarvingKnife");
-				ui.selectable_value(selected, moonlighter::Affinity::Catapults, "Catapults");
-				ui.selectable_value(selected, moonlighter::Affinity::ChainArmourSmithing, "ChainArmourSmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::Channeling, "Channeling");
-				ui.selectable_value(selected, moonlighter::Affinity::Climbing, "Climbing");
-				ui.selectable_value(selected, moonlighter::Affinity::ClothTailoring, "ClothTailoring");
-				ui.selectable_value(selected, moonlighter::Affinity::Clubs, "Clubs");
-				ui.selectable_value(selected, moonlighter::Affinity::CoalMaking, "CoalMaking");
-				ui.selectable_value(selected, moonlighter::Affinity::Cooking, "Cooking");
-				ui.selectable_value(selected, moonlighter::Affinity::DairyFoodMaking, "DairyFoodMaking");
-				ui.selectable_value(selected, moonlighter::Affinity::DefensiveFighting, "DefensiveFighting");
-				ui.selectable_value(selected, moonlighter::Affinity::Digging, "Digging");
-				ui.selectable_value(selected, moonlighter::Affinity::Exorcism, "Exorcism");
-				ui.selectable_value(selected, moonlighter::Affinity::Farming, "Farming");
-				ui.selectable_value(selected, moonlighter::Affinity::Fighting, "Fighting");
-				ui.selectable_value(selected, moonlighter::Affinity::FineCarpentry, "FineCarpentry");
-				ui.selectable_value(selected, moonlighter::Affinity::Firemaking, "Firemaking");
-				ui.selectable_value(selected, moonlighter::Affinity::FirstAid, "FirstAid");
-				ui.selectable_value(selected, moonlighter::Affinity::Fishing, "Fishing");
-				ui.selectable_value(selected, moonlighter::Affinity::Fletching, "Fletching");
-				ui.selectable_value(selected, moonlighter::Affinity::Foraging, "Foraging");
-				ui.selectable_value(selected, moonlighter::Affinity::Forestry, "Forestry");
-				ui.selectable_value(selected, moonlighter::Affinity::Gardening, "Gardening");
-				ui.selectable_value(selected, moonlighter::Affinity::Halberd, "Halberd");
-				ui.selectable_value(selected, moonlighter::Affinity::Hammer, "Hammer");
-				ui.selectable_value(selected, moonlighter::Affinity::Hammers, "Hammers");
-				ui.selectable_value(selected, moonlighter::Affinity::Hatchet, "Hatchet");
-				ui.selectable_value(selected, moonlighter::Affinity::Healing, "Healing");
-				ui.selectable_value(selected, moonlighter::Affinity::HotFoodCooking, "HotFoodCooking");
-				ui.selectable_value(selected, moonlighter::Affinity::HugeAxe, "HugeAxe");
-				ui.selectable_value(selected, moonlighter::Affinity::HugeClub, "HugeClub");
-				ui.selectable_value(selected, moonlighter::Affinity::JewelrySmithing, "JewelrySmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::Knives, "Knives");
-				ui.selectable_value(selected, moonlighter::Affinity::LargeAxe, "LargeAxe");
-				ui.selectable_value(selected, moonlighter::Affinity::LargeMaul, "LargeMaul");
-				ui.selectable_value(selected, moonlighter::Affinity::LargeMetalShield, "LargeMetalShield");
-				ui.selectable_value(selected, moonlighter::Affinity::LargeWoodenShield, "LargeWoodenShield");
-				ui.selectable_value(selected, moonlighter::Affinity::Leatherworking, "Leatherworking");
-				ui.selectable_value(selected, moonlighter::Affinity::LockPicking, "LockPicking");
-				ui.selectable_value(selected, moonlighter::Affinity::Locksmithing, "Locksmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::LongBow, "LongBow");
-				ui.selectable_value(selected, moonlighter::Affinity::LongSpear, "LongSpear");
-				ui.selectable_value(selected, moonlighter::Affinity::Longsword, "Longsword");
-				ui.selectable_value(selected, moonlighter::Affinity::Masonry, "Masonry");
-				ui.selectable_value(selected, moonlighter::Affinity::Mauls, "Mauls");
-				ui.selectable_value(selected, moonlighter::Affinity::Meditating, "Meditating");
-				ui.selectable_value(selected, moonlighter::Affinity::ReflexBow, "ReflexBow");
-				ui.selectable_value(selected, moonlighter::Affinity::MediumMaul, "MediumMaul");
-				ui.selectable_value(selected, moonlighter::Affinity::MediumMetalShield, "MediumMetalShield");
-				ui.selectable_value(selected, moonlighter::Affinity::MediumWoodenShield, "MediumWoodenShield");
-				ui.selectable_value(selected, moonlighter::Affinity::Metallurgy, "Metallurgy");
-				ui.selectable_value(selected, moonlighter::Affinity::Milking, "Milking");
-				ui.selectable_value(selected, moonlighter::Affinity::Milling, "Milling");
-				ui.selectable_value(selected, moonlighter::Affinity::Mind, "Mind");
-				ui.selectable_value(selected, moonlighter::Affinity::MindLogic, "MindLogic");
-				ui.selectable_value(selected, moonlighter::Affinity::MindSpeed, "MindSpeed");
-				ui.selectable_value(selected, moonlighter::Affinity::Mining, "Mining");
-				ui.selectable_value(selected, moonlighter::Affinity::MiscItems, "MiscItems");
-				ui.selectable_value(selected, moonlighter::Affinity::NaturalSubstances, "NaturalSubstances");
-				ui.selectable_value(selected, moonlighter::Affinity::Nature, "Nature");
-				ui.selectable_value(selected, moonlighter::Affinity::NormalFighting, "NormalFighting");
-				ui.selectable_value(selected, moonlighter::Affinity::Papyrusmaking, "Papyrusmaking");
-				ui.selectable_value(selected, moonlighter::Affinity::Paving, "Paving");
-				ui.selectable_value(selected, moonlighter::Affinity::Pickaxe, "Pickaxe");
-				ui.selectable_value(selected, moonlighter::Affinity::PlateArmourSmithing, "PlateArmourSmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::Polearms, "Polearms");
-				ui.selectable_value(selected, moonlighter::Affinity::Pottery, "Pottery");
-				ui.selectable_value(selected, moonlighter::Affinity::Praying, "Praying");
-				ui.selectable_value(selected, moonlighter::Affinity::Preaching, "Preaching");
-				ui.selectable_value(selected, moonlighter::Affinity::Prospecting, "Prospecting");
-				ui.selectable_value(selected, moonlighter::Affinity::Puppeteering, "Puppeteering");
-				ui.selectable_value(selected, moonlighter::Affinity::Rake, "Rake");
-				ui.selectable_value(selected, moonlighter::Affinity::Religion, "Religion");
-				ui.selectable_value(selected, moonlighter::Affinity::Repairing, "Repairing");
-				ui.selectable_value(selected, moonlighter::Affinity::Restoration, "Restoration");
-				ui.selectable_value(selected, moonlighter::Affinity::Ropemaking, "Ropemaking");
-				ui.selectable_value(selected, moonlighter::Affinity::Saw, "Saw");
-				ui.selectable_value(selected, moonlighter::Affinity::Scythe, "Scythe");
-				ui.selectable_value(selected, moonlighter::Affinity::ShieldBashing, "ShieldBashing");
-				ui.selectable_value(selected, moonlighter::Affinity::ShieldSmithing, "ShieldSmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::Shields, "Shields");
-				ui.selectable_value(selected, moonlighter::Affinity::ShipBuilding, "ShipBuilding");
-				ui.selectable_value(selected, moonlighter::Affinity::ShortBow, "ShortBow");
-				ui.selectable_value(selected, moonlighter::Affinity::Shortsword, "Shortsword");
-				ui.selectable_value(selected, moonlighter::Affinity::Shovel, "Shovel");
-				ui.selectable_value(selected, moonlighter::Affinity::Sickle, "Sickle");
-				ui.selectable_value(selected, moonlighter::Affinity::SmallAxe, "SmallAxe");
-				ui.selectable_value(selected, moonlighter::Affinity::SmallMaul, "SmallMaul");
-				ui.selectable_value(selected, moonlighter::Affinity::SmallMetalShield, "SmallMetalShield");
-				ui.selectable_value(selected, moonlighter::Affinity::SmallWoodenShield, "SmallWoodenShield");
-				ui.selectable_value(selected, moonlighter::Affinity::Smithing, "Smithing");
-				ui.selectable_value(selected, moonlighter::Affinity::Soul, "Soul");
-				ui.selectable_value(selected, moonlighter::Affinity::SoulDepth, "SoulDepth");
-				ui.selectable_value(selected, moonlighter::Affinity::SoulStrength, "SoulStrength");
-				ui.selectable_value(selected, moonlighter::Affinity::Staff, "Staff");
-				ui.selectable_value(selected, moonlighter::Affinity::Stealing, "Stealing");
-				ui.selectable_value(selected, moonlighter::Affinity::StoneChisel, "StoneChisel");
-				ui.selectable_value(selected, moonlighter::Affinity::StoneCutting, "StoneCutting");
-				ui.selectable_value(selected, moonlighter::Affinity::Swords, "Swords");
-				ui.selectable_value(selected, moonlighter::Affinity::Tailoring, "Tailoring");
-				ui.selectable_value(selected, moonlighter::Affinity::Taunting, "Taunting");
-				ui.selectable_value(selected, moonlighter::Affinity::Thatching, "Thatching");
-				ui.selectable_value(selected, moonlighter::Affinity::Thievery, "Thievery");
-				ui.selectable_value(selected, moonlighter::Affinity::ToyMaking, "ToyMaking");
-				ui.selectable_value(selected, moonlighter::Affinity::Toys, "Toys");
-				ui.selectable_value(selected, moonlighter::Affinity::Tracking, "Tracking");
-				ui.selectable_value(selected, moonlighter::Affinity::Traps, "Traps");
-				ui.selectable_value(selected, moonlighter::Affinity::Trebuchets, "Trebuchets");
-				ui.selectable_value(selected, moonlighter::Affinity::TwoHandedSword, "TwoHandedSword");
-				ui.selectable_value(selected, moonlighter::Affinity::WarMachines, "WarMachines");
-				ui.selectable_value(selected, moonlighter::Affinity::Warhammer, "Warhammer");
-				ui.selectable_value(selected, moonlighter::Affinity::WeaponHeadsSmithing, "WeaponHeadsSmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::WeaponSmithing, "WeaponSmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::WeaponlessFighting, "WeaponlessFighting");
-				ui.selectable_value(selected, moonlighter::Affinity::Woodcutting, "Woodcutting");
-				ui.selectable_value(selected, moonlighter::Affinity::Yoyo, "Yoyo");
+			ui.horizontal(|ui| {
+				ui.label(format!("Your player number is {}", self.player_number()));
+				if ui.button("Calibrate...").on_hover_text("Work out your player number from a test meal, step by step").clicked() {
+					self.calibration_wizard = Some(calibration::Wizard::default());
+				}
 			});
+			if let Some(notice) = &self.notice {
+				let dismissed = ui
+					.horizontal(|ui| {
+						ui.colored_label(ui.visuals().warn_fg_color, notice);
+						ui.small_button("Dismiss").clicked()
+					})
+					.inner;
+				if dismissed {
+					self.notice = None;
+				}
+			}
 
-			let current_affinity = self.affinity.offset();
-			let selected = &mut self.affinity;
-
-			let combo = egui::ComboBox::from_label(format!("<- Desired affinity ({})", current_affinity)).selected_text(format!("{:?}", selected));
-			combo.show_ui(ui, |ui| {
-				ui.selectable_value(selected, moonlighter::Affinity::AggressiveFighting, "AggressiveFighting");
-				ui.selectable_value(selected, moonlighter::Affinity::Alchemy, "Alchemy");
-				ui.selectable_value(selected, moonlighter::Affinity::AnimalHusbandry, "AnimalHusbandry");
-				ui.selectable_value(selected, moonlighter::Affinity::AnimalTaming, "AnimalTaming");
-				ui.selectable_value(selected, moonlighter::Affinity::Archaeology, "Archaeology");
-				ui.selectable_value(selected, moonlighter::Affinity::Archery, "Archery");
-				ui.selectable_value(selected, moonlighter::Affinity::ArmourSmithing, "ArmourSmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::Axes, "Axes");
-				ui.selectable_value(selected, moonlighter::Affinity::Baking, "Baking");
-				ui.selectable_value(selected, moonlighter::Affinity::Beverages, "Beverages");
-				ui.selectable_value(selected, moonlighter::Affinity::Blacksmithing, "Blacksmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::BladesSmithing, "BladesSmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::Body, "Body");
-				ui.selectable_value(selected, moonlighter::Affinity::BodyControl, "BodyControl");
-				ui.selectable_value(selected, moonlighter::Affinity::BodyStamina, "BodyStamina");
-				ui.selectable_value(selected, moonlighter::Affinity::BodyStrength, "BodyStrength");
-				ui.selectable_value(selected, moonlighter::Affinity::Botanizing, "Botanizing");
-				ui.selectable_value(selected, moonlighter::Affinity::Bowyery, "Bowyery");
-				ui.selectable_value(selected, moonlighter::Affinity::Butchering, "Butchering");
-				ui.selectable_value(selected, moonlighter::Affinity::ButcheringKnife, "ButcheringKnife");
-				ui.selectable_value(selected, moonlighter::Affinity::Carpentry, "Carpentry");
-				ui.selectable_value(selected, moonlighter::Affinity::Cartography, "Cartography");
-				ui.selectable_value(selected, moonlighter::Affinity::CarvingKnife, "CarvingKnife");
-				ui.selectable_value(selected, moonlighter::Affinity::Catapults, "Catapults");
-				ui.selectable_value(selected, moonlighter::Affinity::ChainArmourSmithing, "ChainArmourSmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::Channeling, "Channeling");
-				ui.selectable_value(selected, moonlighter::Affinity::Climbing, "Climbing");
-				ui.selectable_value(selected, moonlighter::Affinity::ClothTailoring, "ClothTailoring");
-				ui.selectable_value(selected, moonlighter::Affinity::Clubs, "Clubs");
-				ui.selectable_value(selected, moonlighter::Affinity::CoalMaking, "CoalMaking");
-				ui.selectable_value(selected, moonlighter::Affinity::Cooking, "Cooking");
-				ui.selectable_value(selected, moonlighter::Affinity::DairyFoodMaking, "DairyFoodMaking");
-				ui.selectable_value(selected, moonlighter::Affinity::DefensiveFighting, "DefensiveFighting");
-				ui.selectable_value(selected, moonlighter::Affinity::Digging, "Digging");
-				ui.selectable_value(selected, moonlighter::Affinity::Exorcism, "Exorcism");
-				ui.selectable_value(selected, moonlighter::Affinity::Farming, "Farming");
-				ui.selectable_value(selected, moonlighter::Affinity::Fighting, "Fighting");
-				ui.selectable_value(selected, moonlighter::Affinity::FineCarpentry, "FineCarpentry");
-				ui.selectable_value(selected, moonlighter::Affinity::Firemaking, "Firemaking");
-				ui.selectable_value(selected, moonlighter::Affinity::FirstAid, "FirstAid");
-				ui.selectable_value(selected, moonlighter::Affinity::Fishing, "Fishing");
-				ui.selectable_value(selected, moonlighter::Affinity::Fletching, "Fletching");
-				ui.selectable_value(selected, moonlighter::Affinity::Foraging, "Foraging");
-				ui.selectable_value(selected, moonlighter::Affinity::Forestry, "Forestry");
-				ui.selectable_value(selected, moonlighter::Affinity::Gardening, "Gardening");
-				ui.selectable_value(selected, moonlighter::Affinity::Halberd, "Halberd");
-				ui.selectable_value(selected, moonlighter::Affinity::Hammer, "Hammer");
-				ui.selectable_value(selected, moonlighter::Affinity::Hammers, "Hammers");
-				ui.selectable_value(selected, moonlighter::Affinity::Hatchet, "Hatchet");
-				ui.selectable_value(selected, moonlighter::Affinity::Healing, "Healing");
-				ui.selectable_value(selected, moonlighter::Affinity::HotFoodCooking, "HotFoodCooking");
-				ui.selectable_value(selected, moonlighter::Affinity::HugeAxe, "HugeAxe");
-				ui.selectable_value(selected, moonlighter::Affinity::HugeClub, "HugeClub");
-				ui.selectable_value(selected, moonlighter::Affinity::JewelrySmithing, "JewelrySmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::Knives, "Knives");
-				ui.selectable_value(selected, moonlighter::Affinity::LargeAxe, "LargeAxe");
-				ui.selectable_value(selected, moonlighter::Affinity::LargeMaul, "LargeMaul");
-				ui.selectable_value(selected, moonlighter::Affinity::LargeMetalShield, "LargeMetalShield");
-				ui.selectable_value(selected, moonlighter::Affinity::LargeWoodenShield, "LargeWoodenShield");
-				ui.selectable_value(selected, moonlighter::Affinity::Leatherworking, "Leatherworking");
-				ui.selectable_value(selected, moonlighter::Affinity::LockPicking, "LockPicking");
-				ui.selectable_value(selected, moonlighter::Affinity::Locksmithing, "Locksmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::LongBow, "LongBow");
-				ui.selectable_value(selected, moonlighter::Affinity::LongSpear, "LongSpear");
-				ui.selectable_value(selected, moonlighter::Affinity::Longsword, "Longsword");
-				ui.selectable_value(selected, moonlighter::Affinity::Masonry, "Masonry");
-				ui.selectable_value(selected, moonlighter::Affinity::Mauls, "Mauls");
-				ui.selectable_value(selected, moonlighter::Affinity::Meditating, "Meditating");
-				ui.selectable_value(selected, moonlighter::Affinity::ReflexBow, "ReflexBow");
-				ui.selectable_value(selected, moonlighter::Affinity::MediumMaul, "MediumMaul");
-				ui.selectable_value(selected, moonlighter::Affinity::MediumMetalShield, "MediumMetalShield");
-				ui.selectable_value(selected, moonlighter::Affinity::MediumWoodenShield, "MediumWoodenShield");
-				ui.selectable_value(selected, moonlighter::Affinity::Metallurgy, "Metallurgy");
-				ui.selectable_value(selected, moonlighter::Affinity::Milking, "Milking");
-				ui.selectable_value(selected, moonlighter::Affinity::Milling, "Milling");
-				ui.selectable_value(selected, moonlighter::Affinity::Mind, "Mind");
-				ui.selectable_value(selected, moonlighter::Affinity::MindLogic, "MindLogic");
-				ui.selectable_value(selected, moonlighter::Affinity::MindSpeed, "MindSpeed");
-				ui.selectable_value(selected, moonlighter::Affinity::Mining, "Mining");
-				ui.selectable_value(selected, moonlighter::Affinity::MiscItems, "MiscItems");
-				ui.selectable_value(selected, moonlighter::Affinity::NaturalSubstances, "NaturalSubstances");
-				ui.selectable_value(selected, moonlighter::Affinity::Nature, "Nature");
-				ui.selectable_value(selected, moonlighter::Affinity::NormalFighting, "NormalFighting");
-				ui.selectable_value(selected, moonlighter::Affinity::Papyrusmaking, "Papyrusmaking");
-				ui.selectable_value(selected, moonlighter::Affinity::Paving, "Paving");
-				ui.selectable_value(selected, moonlighter::Affinity::Pickaxe, "Pickaxe");
-				ui.selectable_value(selected, moonlighter::Affinity::PlateArmourSmithing, "PlateArmourSmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::Polearms, "Polearms");
-				ui.selectable_value(selected, moonlighter::Affinity::Pottery, "Pottery");
-				ui.selectable_value(selected, moonlighter::Affinity::Praying, "Praying");
-				ui.selectable_value(selected, moonlighter::Affinity::Preaching, "Preaching");
-				ui.selectable_value(selected, moonlighter::Affinity::Prospecting, "Prospecting");
-				ui.selectable_value(selected, moonlighter::Affinity::Puppeteering, "Puppeteering");
-				ui.selectable_value(selected, moonlighter::Affinity::Rake, "Rake");
-				ui.selectable_value(selected, moonlighter::Affinity::Religion, "Religion");
-				ui.selectable_value(selected, moonlighter::Affinity::Repairing, "Repairing");
-				ui.selectable_value(selected, moonlighter::Affinity::Restoration, "Restoration");
-				ui.selectable_value(selected, moonlighter::Affinity::Ropemaking, "Ropemaking");
-				ui.selectable_value(selected, moonlighter::Affinity::Saw, "Saw");
-				ui.selectable_value(selected, moonlighter::Affinity::Scythe, "Scythe");
-				ui.selectable_value(selected, moonlighter::Affinity::ShieldBashing, "ShieldBashing");
-				ui.selectable_value(selected, moonlighter::Affinity::ShieldSmithing, "ShieldSmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::Shields, "Shields");
-				ui.selectable_value(selected, moonlighter::Affinity::ShipBuilding, "ShipBuilding");
-				ui.selectable_value(selected, moonlighter::Affinity::ShortBow, "ShortBow");
-				ui.selectable_value(selected, moonlighter::Affinity::Shortsword, "Shortsword");
-				ui.selectable_value(selected, moonlighter::Affinity::Shovel, "Shovel");
-				ui.selectable_value(selected, moonlighter::Affinity::Sickle, "Sickle");
-				ui.selectable_value(selected, moonlighter::Affinity::SmallAxe, "SmallAxe");
-				ui.selectable_value(selected, moonlighter::Affinity::SmallMaul, "SmallMaul");
-				ui.selectable_value(selected, moonlighter::Affinity::SmallMetalShield, "SmallMetalShield");
-				ui.selectable_value(selected, moonlighter::Affinity::SmallWoodenShield, "SmallWoodenShield");
-				ui.selectable_value(selected, moonlighter::Affinity::Smithing, "Smithing");
-				ui.selectable_value(selected, moonlighter::Affinity::Soul, "Soul");
-				ui.selectable_value(selected, moonlighter::Affinity::SoulDepth, "SoulDepth");
-				ui.selectable_value(selected, moonlighter::Affinity::SoulStrength, "SoulStrength");
-				ui.selectable_value(selected, moonlighter::Affinity::Staff, "Staff");
-				ui.selectable_value(selected, moonlighter::Affinity::Stealing, "Stealing");
-				ui.selectable_value(selected, moonlighter::Affinity::StoneChisel, "StoneChisel");
-				ui.selectable_value(selected, moonlighter::Affinity::StoneCutting, "StoneCutting");
-				ui.selectable_value(selected, moonlighter::Affinity::Swords, "Swords");
-				ui.selectable_value(selected, moonlighter::Affinity::Tailoring, "Tailoring");
-				ui.selectable_value(selected, moonlighter::Affinity::Taunting, "Taunting");
-				ui.selectable_value(selected, moonlighter::Affinity::Thatching, "Thatching");
-				ui.selectable_value(selected, moonlighter::Affinity::Thievery, "Thievery");
-				ui.selectable_value(selected, moonlighter::Affinity::ToyMaking, "ToyMaking");
-				ui.selectable_value(selected, moonlighter::Affinity::Toys, "Toys");
-				ui.selectable_value(selected, moonlighter::Affinity::Tracking, "Tracking");
-				ui.selectable_value(selected, moonlighter::Affinity::Traps, "Traps");
-				ui.selectable_value(selected, moonlighter::Affinity::Trebuchets, "Trebuchets");
-				ui.selectable_value(selected, moonlighter::Affinity::TwoHandedSword, "TwoHandedSword");
-				ui.selectable_value(selected, moonlighter::Affinity::WarMachines, "WarMachines");
-				ui.selectable_value(selected, moonlighter::Affinity::Warhammer, "Warhammer");
-				ui.selectable_value(selected, moonlighter::Affinity::WeaponHeadsSmithing, "WeaponHeadsSmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::WeaponSmithing, "WeaponSmithing");
-				ui.selectable_value(selected, moonlighter::Affinity::WeaponlessFighting, "WeaponlessFighting");
-				ui.selectable_value(selected, moonlighter::Affinity::Woodcutting, "Woodcutting");
-				ui.selectable_value(selected, moonlighter::Affinity::Yoyo, "Yoyo");
+			ui.add(egui::Slider::new(&mut self.options.custom_offset, 0..=255).text("Custom offset (+1 for each rare item used, +2 supreme, +3 fantastic)"));
+			ui.checkbox(&mut self.options.full_cereals, "Use 4 cereals");
+			ui.checkbox(&mut self.options.complex_processing, "Use complex processings options (fried and roasted)");
+			ui.horizontal(|ui| {
+				exclusion_menu(ui, "Excluded vegetables", variants::vegetables(), &mut self.exclusions.vegetables);
+				exclusion_menu(ui, "Excluded processings", variants::processings(), &mut self.exclusions.processings);
 			});
 
-			if self.generating_state == GeneratingState::GenerateClicked {
-				ui.label("Generating...");
-				self.generating_state = GeneratingState::Generating;
-				ctx.request_repaint();
-			} else if self.generating_state == GeneratingState::Idle {
-				if ui.button("Generate").clicked() {
-					self.recipe = None;
-					self.generating_state = GeneratingState::GenerateClicked;
-					ctx.request_repaint();
-				};
+			ui.add(egui::Slider::new(&mut self.options.vegetables, 1..=12).text("Unique vegetables to use"));
+			ui.add(egui::Slider::new(&mut self.options.max_fillers, 0..=worker::MAX_FILLERS).text("Maximum sugar and barley to add to adjust affinity"));
+			ui.add(egui::Slider::new(&mut self.alternatives, 1..=10).text("Recipes to list (more than one also tries nearby settings, which takes longer)"));
+
+			egui::CollapsingHeader::new("Check calibration").show(ui, |ui| self.observations_ui(ui));
+			egui::CollapsingHeader::new("Inventory").show(ui, |ui| self.inventory_ui(ui));
+			egui::CollapsingHeader::new("Batch").show(ui, |ui| self.batch_ui(ui, ctx));
+			egui::CollapsingHeader::new("Export and import").show(ui, |ui| self.export_ui(ui));
+
+			let bear_meal_label = format!("<- Bear + Corn + Pan + Oven gives me ({})", self.bear_meal_affinity.offset());
+			affinity_picker::affinity_picker(ui, "bear_meal_affinity", bear_meal_label, &mut self.bear_meal_affinity, &mut self.affinity_picks);
+			let affinity_label = format!("<- Desired affinity ({})", self.options.affinity.offset());
+			affinity_picker::affinity_picker(ui, "affinity", affinity_label, &mut self.options.affinity, &mut self.affinity_picks);
+
+			match &self.generating_state {
+				GeneratingState::Generating { progress, .. } => {
+					let (cancel_clicked, keep_clicked) = ui
+						.horizontal(|ui| {
+							ui.spinner();
+							ui.label("Generating...");
+							let cancel_clicked = ui.button("Cancel").clicked();
+							let keep_clicked = self.recipe.is_some() && ui.button("Stop and keep this recipe").clicked();
+							(cancel_clicked, keep_clicked)
+						})
+						.inner;
+					progress_ui(ui, progress, now);
+					if cancel_clicked {
+						self.cancel_generating();
+					} else if keep_clicked {
+						self.stop_generating();
+					}
+				}
+				GeneratingState::Idle | GeneratingState::Cancelled => {
+					let generate_clicked = ui
+						.horizontal(|ui| {
+							let clicked = ui.button("Generate").clicked();
+							if matches!(self.generating_state, GeneratingState::Cancelled) {
+								if self.stopping.is_some() {
+									ui.spinner();
+									ui.label("Cancelling...").on_hover_text("The solver is finishing the run it's on; a new search will start after it");
+								} else {
+									ui.label("Generation cancelled.");
+								}
+							}
+							if self.recipe_cached && ui.button("Search again").on_hover_text("These results are from an earlier search with the same settings").clicked() {
+								self.start_generating(ctx);
+							}
+							if !self.cache.is_empty()
+								&& ui
+									.small_button("Clear cache")
+									.on_hover_text(format!("Forget the results of {} earlier searches", self.cache.len()))
+									.clicked()
+							{
+								self.cache.clear();
+								self.recipe_cached = false;
+							}
+							clicked
+						})
+						.inner;
+					if generate_clicked {
+						self.generate(ctx);
+					}
+				}
 			}
 
-			if let Some(mut recipe) = self.recipe.clone() {
-				ui.label(format!("Best recipe found with {} vegetables!", recipe.vegs.len()));
-				ui.checkbox(&mut self.checkbox_states[0], "water");
-				ui.checkbox(&mut self.checkbox_states[1], format!("{} sugars", recipe.sugars));
-				ui.checkbox(&mut self.checkbox_states[1], format!("{} barleys", recipe.barleys));
-				for (idx, cereal) in recipe.cereals.iter().enumerate() {
-					ui.checkbox(&mut self.checkbox_states[2 + idx], format!("{:?}", cereal));
+			if let Some(recipe) = self.recipe.clone() {
+				if self.recipe_optimal {
+					ui.label(format!("Best recipe found with {} vegetables!", recipe.vegs.len()));
+				} else if matches!(self.generating_state, GeneratingState::Generating { .. }) {
+					ui.label(format!("Best so far, with {} vegetables. Still looking for the optimal recipe...", recipe.vegs.len()));
+				} else {
+					ui.label(format!("Best found, with {} vegetables. Not proven optimal for the settings above.", recipe.vegs.len()));
 				}
-				for (idx, veg) in recipe.vegs.iter().enumerate() {
-					while recipe.processings[0].1 == 0 {
-						recipe.processings.remove(0);
+				if self.inventory.enabled {
+					ui.label(format!("Your inventory is enough for {} batches.", self.inventory.batches(&recipe)));
+				}
+				ui.horizontal(|ui| {
+					if ui.button("Brew step by step").clicked() {
+						self.brewing_step = Some(0);
 					}
-					let processing = match recipe.processings[0] {
-						(processing, 1) => {
-							recipe.processings.remove(0);
-							processing
-						}
-						(processing, _) => {
-							recipe.processings[0].1 -= 1;
-							processing
-						}
-					};
-					ui.checkbox(&mut self.checkbox_states[6 + idx], format!("{:?} {:?}", veg, processing));
+					if ui.button("Copy").on_hover_text("Copy the recipe as text to paste in chat").clicked() {
+						let origin = self.shown_origin();
+						ui.ctx().copy_text(core::chat_text(&recipe, &origin.options, origin.player_number));
+					}
+					if ui.button("Copy code").on_hover_text("Copy a short code that opens this recipe in V12").clicked() {
+						self.copy_share_code(ui.ctx(), &recipe);
+					}
+					if ui.button("Copy link").on_hover_text("Copy a link to the web app that opens on this recipe").clicked() {
+						ui.ctx().copy_text(self.link().to_url());
+					}
+				});
+				for item in &mut self.checklist {
+					ui.checkbox(&mut item.done, item.step.to_string());
 				}
 			}
 
+			if self.diagnosis.is_some() && !matches!(self.generating_state, GeneratingState::Generating { .. }) {
+				ui.separator();
+				self.diagnosis_ui(ui);
+			}
+
+			if self.alternative_recipes.len() > 1 && !matches!(self.generating_state, GeneratingState::Generating { .. }) {
+				ui.separator();
+				self.alternatives_ui(ui);
+			}
+
 			ui.separator();
 
 			ui.label("Tip: if you want to increase the volume of moonshine without changing affinity, add packs of 3 fried pumpkins");
@@ -467,6 +1455,49 @@ impl eframe::App for TemplateApp {
 	}
 }
 
+/// A drop-down of checkboxes, one per name; ticked names go in `excluded`.
+fn exclusion_menu(ui: &mut egui::Ui, label: &str, names: &[&str], excluded: &mut std::collections::BTreeSet<String>) {
+	let title = if excluded.is_empty() { label.to_owned() } else { format!("{label} ({})", excluded.len()) };
+	ui.menu_button(title, |ui| {
+		for name in names {
+			let mut checked = excluded.contains(*name);
+			if ui.checkbox(&mut checked, *name).changed() {
+				if checked {
+					excluded.insert((*name).to_owned());
+				} else {
+					excluded.remove(*name);
+				}
+			}
+		}
+		if !excluded.is_empty() && ui.button("Clear").clicked() {
+			excluded.clear();
+		}
+	});
+}
+
+fn progress_ui(ui: &mut egui::Ui, tracker: &worker::ProgressTracker, now: f64) {
+	let text = match tracker.progress() {
+		Some(progress) if progress.diagnosing => "No recipe, looking into why".to_owned(),
+		Some(progress) => format!("Run {} of {} ({} vegetables)", progress.run + 1, progress.runs, progress.vegetables),
+		None => String::new(),
+	};
+	ui.add(egui::ProgressBar::new(tracker.fraction(now)).text(text));
+
+	let mut status = format!("Elapsed {}", format_duration(tracker.elapsed(now)));
+	if let Some(eta) = tracker.eta(now) {
+		status += &format!(", about {} left", format_duration(eta));
+	}
+	if let Some(best_length) = tracker.progress().and_then(|progress| progress.best_length) {
+		status += &format!(". Best so far: {best_length} ingredients");
+	}
+	ui.label(status);
+}
+
+fn format_duration(seconds: f64) -> String {
+	let seconds = seconds.round() as u64;
+	if seconds < 60 { format!("{seconds}s") } else { format!("{}m {:02}s", seconds / 60, seconds % 60) }
+}
+
 fn powered_by_egui_and_eframe(ui: &mut egui::Ui) {
 	ui.horizontal(|ui| {
 		ui.spacing_mut().item_spacing.x = 0.0;
@@ -477,3 +1508,73 @@ fn powered_by_egui_and_eframe(ui: &mut egui::Ui) {
 		ui.label(".");
 	});
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Default)]
+	struct Memory(std::collections::HashMap<String, String>);
+
+	impl eframe::Storage for Memory {
+		fn get_string(&self, key: &str) -> Option<String> {
+			self.0.get(key).cloned()
+		}
+
+		fn set_string(&mut self, key: &str, value: String) {
+			self.0.insert(key.to_owned(), value);
+		}
+
+		fn flush(&mut self) {}
+	}
+
+	/// The app state as saved before the options were grouped.
+	#[derive(serde::Serialize)]
+	struct OldApp {
+		full_cereals: bool,
+		complex_processing: bool,
+		custom_offset: u64,
+		vegetables: u64,
+		max_fillers: u64,
+		checkbox_states: [bool; 18],
+		affinity: moonlighter::Affinity,
+		bear_meal_affinity: moonlighter::Affinity,
+		recipe: Option<moonlighter::Recipe>,
+	}
+
+	#[test]
+	fn state_saved_before_the_options_were_grouped_loads() {
+		let old = OldApp {
+			full_cereals: false,
+			complex_processing: true,
+			custom_offset: 3,
+			vegetables: 7,
+			max_fillers: 20,
+			checkbox_states: [true; 18],
+			affinity: moonlighter::Affinity::Carpentry,
+			bear_meal_affinity: moonlighter::Affinity::CoalMaking,
+			recipe: Some(core::fixtures::recipe(&[(0, 0), (1, 1)], 0, 4, 0)),
+		};
+		let mut storage = Memory::default();
+		eframe::set_value(&mut storage, eframe::APP_KEY, &old);
+
+		let mut app = TemplateApp::load(Some(&storage));
+		let options = core::Options {
+			affinity: old.affinity,
+			vegetables: 7,
+			max_fillers: 20,
+			complex_processing: true,
+			full_cereals: false,
+			custom_offset: 3,
+		};
+		assert!(app.options == options);
+		assert_eq!(app.bear_meal_affinity, old.bear_meal_affinity);
+		assert_eq!(app.recipe.as_ref().map(core::steps), old.recipe.as_ref().map(core::steps));
+
+		// Saved again, the options are grouped, and changes to them stick.
+		app.options.vegetables = 4;
+		eframe::set_value(&mut storage, eframe::APP_KEY, &app);
+		let again = TemplateApp::load(Some(&storage));
+		assert!(again.options == core::Options { vegetables: 4, ..options });
+	}
+}
@@ -0,0 +1,259 @@
+//! Solving a queue of affinities one after another in the background, for a whole brewing session.
+
+use crate::{core, worker};
+
+#[derive(Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Status {
+	Queued,
+	Running,
+	Found,
+	/// Only nearby settings gave a recipe.
+	FoundNearby,
+	NotFound,
+	Cancelled,
+}
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub struct Entry {
+	pub request: worker::SearchRequest,
+	pub status: Status,
+	pub recipe: Option<moonlighter::Recipe>,
+	/// The nearby settings `recipe` was found with, when it's [`Status::FoundNearby`].
+	#[serde(default)]
+	pub run: Option<worker::Run>,
+}
+
+impl Entry {
+	/// The settings `recipe` is the solver's answer for, if they're known.
+	pub fn recipe_options(&self) -> Option<core::Options> {
+		match (self.status, &self.run) {
+			(Status::Found, _) => Some(self.request.options.clone()),
+			(Status::FoundNearby, Some(run)) => {
+				let mut options = self.request.options.clone();
+				run.apply(&mut options);
+				Some(options)
+			}
+			_ => None,
+		}
+	}
+}
+
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Batch {
+	pub entries: Vec<Entry>,
+	/// The search going now, and the index of its entry.
+	#[serde(skip)]
+	running: Option<(usize, worker::Job)>,
+	/// A cancelled search that hasn't stopped yet. The next search waits for it.
+	#[serde(skip)]
+	stopping: Option<worker::Job>,
+}
+
+impl Batch {
+	pub fn push(&mut self, request: worker::SearchRequest) {
+		self.entries.push(Entry {
+			request,
+			status: Status::Queued,
+			recipe: None,
+			run: None,
+		});
+	}
+
+	/// Returns whether it was the entry being searched, which stops the search.
+	pub fn remove(&mut self, index: usize) -> bool {
+		let was_running = match &mut self.running {
+			Some((running, _)) if *running == index => {
+				self.cancel();
+				true
+			}
+			Some((running, _)) if *running > index => {
+				*running -= 1;
+				false
+			}
+			_ => false,
+		};
+		if index < self.entries.len() {
+			self.entries.remove(index);
+		}
+		was_running
+	}
+
+	pub fn is_running(&self) -> bool {
+		self.running.is_some()
+	}
+
+	/// Whether a cancelled search is still finishing its solver run.
+	pub fn is_stopping(&self) -> bool {
+		self.stopping.is_some()
+	}
+
+	pub fn has_queued(&self) -> bool {
+		self.entries.iter().any(|entry| entry.status == Status::Queued)
+	}
+
+	/// Starts on the next queued entry, if nothing is running.
+	pub fn start(&mut self, ctx: &egui::Context) {
+		if self.running.is_some() {
+			return;
+		}
+		let Some((index, entry)) = self.entries.iter_mut().enumerate().find(|(_, entry)| entry.status == Status::Queued) else {
+			return;
+		};
+		entry.status = Status::Running;
+		self.running = Some((index, worker::Job::spawn(ctx, &entry.request, self.stopping.take())));
+	}
+
+	/// Takes in what the running search has reported, moving on to the next entry when it's done.
+	pub fn poll(&mut self, ctx: &egui::Context) {
+		if self.stopping.as_ref().is_some_and(worker::Job::has_stopped) {
+			self.stopping = None;
+		}
+		let Some((index, job)) = &self.running else {
+			return;
+		};
+		let Some(entry) = self.entries.get_mut(*index) else {
+			self.running = None;
+			return;
+		};
+		while let Some(message) = job.poll() {
+			match message {
+				worker::Message::Best(recipe) => {
+					if recipe.is_some() {
+						entry.status = Status::Found;
+						entry.recipe = recipe;
+					}
+				}
+				worker::Message::Finished(alternatives) => {
+					if entry.status != Status::Found {
+						let nearest = alternatives.into_iter().next();
+						entry.status = if nearest.is_some() { Status::FoundNearby } else { Status::NotFound };
+						(entry.recipe, entry.run) = nearest.map(|alternative| (alternative.recipe, alternative.run)).unzip();
+					}
+					self.running = None;
+					self.start(ctx);
+					return;
+				}
+				worker::Message::Progress(_) | worker::Message::Candidate(_) | worker::Message::Diagnosis(_) => {}
+			}
+		}
+	}
+
+	/// Stops the running search, and leaves the rest of the queue for later.
+	pub fn cancel(&mut self) {
+		if let Some((index, job)) = self.running.take() {
+			job.cancel();
+			self.stopping = Some(job);
+			if let Some(entry) = self.entries.get_mut(index) {
+				entry.status = Status::Cancelled;
+			}
+		}
+	}
+
+	/// Queues the cancelled entries again.
+	pub fn retry_cancelled(&mut self) {
+		for entry in &mut self.entries {
+			if entry.status == Status::Cancelled {
+				entry.status = Status::Queued;
+			}
+		}
+	}
+
+	/// Entries that were running when the app was closed are picked up as cancelled.
+	pub fn restore(&mut self) {
+		for entry in &mut self.entries {
+			if entry.status == Status::Running {
+				entry.status = Status::Cancelled;
+			}
+		}
+	}
+
+	/// Every recipe found, as plain text to paste somewhere.
+	pub fn export_text(&self) -> String {
+		self.entries
+			.iter()
+			.filter_map(|entry| {
+				let recipe = entry.recipe.as_ref()?;
+				let steps: String = core::steps(recipe).iter().map(|step| format!("  {step}\n")).collect();
+				Some(format!("{:?} (player number {})\n{steps}", entry.request.options.affinity, entry.request.player_number))
+			})
+			.collect::<Vec<_>>()
+			.join("\n")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn batch(statuses: &[Status]) -> Batch {
+		let mut batch = Batch::default();
+		for status in statuses {
+			batch.push(worker::SearchRequest {
+				options: core::Options {
+					vegetables: 1,
+					..core::Options::default()
+				},
+				player_number: 57,
+				alternatives: 1,
+				inventory: Default::default(),
+				exclusions: Default::default(),
+				candidates: false,
+			});
+			if let Some(entry) = batch.entries.last_mut() {
+				entry.status = *status;
+			}
+		}
+		batch
+	}
+
+	#[test]
+	fn removing_an_earlier_entry_keeps_track_of_the_running_one() {
+		let mut batch = batch(&[Status::Found, Status::NotFound, Status::Queued, Status::Queued]);
+		batch.start(&egui::Context::default());
+		assert!(batch.entries.get(2).is_some_and(|entry| entry.status == Status::Running));
+
+		assert!(!batch.remove(0));
+		assert!(!batch.remove(2));
+		assert!(batch.is_running());
+		assert!(batch.remove(1));
+		assert!(!batch.is_running());
+		assert!(batch.entries.iter().map(|entry| entry.status).eq([Status::NotFound]));
+	}
+
+	#[test]
+	fn cancelled_entries_are_queued_again() {
+		let mut batch = batch(&[Status::Queued, Status::Found, Status::Cancelled]);
+		batch.start(&egui::Context::default());
+		batch.cancel();
+		assert!(!batch.is_running());
+		assert!(batch.entries.iter().map(|entry| entry.status).eq([Status::Cancelled, Status::Found, Status::Cancelled]));
+
+		batch.retry_cancelled();
+		assert!(batch.entries.iter().map(|entry| entry.status).eq([Status::Queued, Status::Found, Status::Queued]));
+		assert!(batch.has_queued());
+	}
+
+	#[test]
+	fn nearby_recipes_show_with_their_settings() {
+		let mut batch = batch(&[Status::Found, Status::FoundNearby, Status::FoundNearby]);
+		let run = worker::Run {
+			vegetables: 1,
+			complex_processing: true,
+			full_cereals: false,
+		};
+		if let Some(entry) = batch.entries.get_mut(1) {
+			entry.run = Some(run.clone());
+		}
+		let options: Vec<_> = batch.entries.iter().map(Entry::recipe_options).collect();
+		assert!(options.first().is_some_and(|options| options.as_ref().map(worker::Run::of)
+			== Some(worker::Run {
+				complex_processing: false,
+				full_cereals: true,
+				..run.clone()
+			})));
+		assert!(options.get(1).is_some_and(|options| options.as_ref().map(worker::Run::of) == Some(run)));
+		// Saved before the settings were kept.
+		assert!(options.get(2).is_some_and(Option::is_none));
+	}
+}
@@ -0,0 +1,277 @@
+#![warn(clippy::all, rust_2018_idioms)]
+
+//! Finds a recipe without the GUI, for scripts and terminals.
+
+use std::io::Write as _;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: v12-cli --calibration <AFFINITY> --affinity <AFFINITY> [OPTIONS]
+
+Options:
+  --calibration <AFFINITY>  What bear meat and corn, fried in a pan and cooked in an oven, give you
+  --player-number <N>       Your player number, instead of --calibration
+  --affinity <AFFINITY>     The affinity to brew for
+  --vegetables <N>          Unique vegetables to use [default: 12]
+  --max-fillers <N>         Most sugars and barleys to add [default: 80]
+  --cereals <1|4>           Cereals to use [default: 4]
+  --complex-processing      Use complex processings (fried and roasted)
+  --custom-offset <N>       Extra offset to add [default: 0]
+  --alternatives <N>        Recipes to list, the best one included [default: 1]
+  --format <FORMAT>         text, or json or csv for just the best recipe [default: text]
+  --list-affinities         List the affinity names and exit
+  -h, --help                Show this help
+
+Affinity names are matched ignoring case, spaces and punctuation.";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+	Text,
+	Json,
+	Csv,
+}
+
+/// What the arguments ask for.
+enum Command {
+	Search { request: v12::SearchRequest, format: Format },
+	ListAffinities,
+	Help,
+}
+
+fn main() -> ExitCode {
+	match run() {
+		Ok(code) => code,
+		Err(error) => {
+			writeln!(std::io::stderr(), "error: {error}\n\n{USAGE}").ok();
+			ExitCode::from(2)
+		}
+	}
+}
+
+fn run() -> Result<ExitCode, String> {
+	let (request, format) = match parse(std::env::args().skip(1))? {
+		Command::Search { request, format } => (request, format),
+		Command::ListAffinities => {
+			let mut out = std::io::stdout().lock();
+			for name in v12::affinities() {
+				writeln!(out, "{name}").map_err(|error| error.to_string())?;
+			}
+			return Ok(ExitCode::SUCCESS);
+		}
+		Command::Help => {
+			writeln!(std::io::stdout(), "{USAGE}").map_err(|error| error.to_string())?;
+			return Ok(ExitCode::SUCCESS);
+		}
+	};
+
+	let mut best = None;
+	let mut diagnosis = None;
+	let mut alternatives = Vec::new();
+	v12::search(&request, &v12::CancelToken::default(), &mut |message| match message {
+		v12::Message::Best(recipe) => best = recipe,
+		v12::Message::Diagnosis(found) => diagnosis = Some(found),
+		v12::Message::Finished(found) => alternatives = found,
+		v12::Message::Progress(_) | v12::Message::Candidate(_) => {}
+	});
+
+	let printed = match (format, &best) {
+		(Format::Text, _) => print_results(&request, best.as_ref(), diagnosis.as_ref(), &alternatives),
+		(Format::Json | Format::Csv, Some(recipe)) => {
+			let saved = v12::export::SavedRecipe {
+				options: request.options.clone(),
+				player_number: request.player_number,
+				recipe: recipe.clone(),
+			};
+			let mut out = std::io::stdout().lock();
+			if matches!(format, Format::Json) {
+				writeln!(out, "{}", saved.to_json())
+			} else {
+				out.write_all(saved.to_csv().as_bytes())
+			}
+		}
+		(Format::Json | Format::Csv, None) => writeln!(std::io::stderr(), "No recipe for these settings."),
+	};
+	printed.map_err(|error| error.to_string())?;
+	Ok(if best.is_some() { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+}
+
+fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+	let mut calibration = None;
+	let mut player_number = None;
+	let mut affinity = None;
+	let mut format = Format::Text;
+	let mut request = v12::SearchRequest {
+		options: v12::core::Options {
+			max_fillers: v12::MAX_FILLERS,
+			..v12::core::Options::default()
+		},
+		player_number: 0,
+		alternatives: 1,
+		inventory: v12::Inventory::default(),
+		exclusions: v12::Exclusions::default(),
+		candidates: false,
+	};
+
+	let mut args = args.into_iter();
+	while let Some(arg) = args.next() {
+		let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+		match arg.as_str() {
+			"--calibration" => calibration = Some(affinity_arg(&value()?)?),
+			"--player-number" => player_number = Some(number_arg(&arg, &value()?)?),
+			"--affinity" => affinity = Some(affinity_arg(&value()?)?),
+			"--vegetables" => request.options.vegetables = number_arg(&arg, &value()?)?,
+			"--max-fillers" => request.options.max_fillers = number_arg(&arg, &value()?)?,
+			"--cereals" => {
+				request.options.full_cereals = match value()?.as_str() {
+					"1" => false,
+					"4" => true,
+					other => return Err(format!("--cereals must be 1 or 4, not {other}")),
+				};
+			}
+			"--complex-processing" => request.options.complex_processing = true,
+			"--custom-offset" => request.options.custom_offset = number_arg(&arg, &value()?)?,
+			"--alternatives" => request.alternatives = number_arg(&arg, &value()?)?.max(1),
+			"--format" => {
+				format = match value()?.as_str() {
+					"text" => Format::Text,
+					"json" => Format::Json,
+					"csv" => Format::Csv,
+					other => return Err(format!("--format must be text, json or csv, not {other}")),
+				};
+			}
+			"--list-affinities" => return Ok(Command::ListAffinities),
+			"-h" | "--help" => return Ok(Command::Help),
+			other => return Err(format!("unexpected argument {other}")),
+		}
+	}
+
+	request.options.affinity = affinity.ok_or("--affinity is required")?;
+	request.player_number = match (calibration, player_number) {
+		(Some(calibration), None) => v12::core::player_number(&calibration),
+		(None, Some(player_number)) => player_number % 138,
+		(Some(_), Some(_)) => return Err("give either --calibration or --player-number, not both".to_owned()),
+		(None, None) => return Err("--calibration or --player-number is required".to_owned()),
+	};
+	Ok(Command::Search { request, format })
+}
+
+fn affinity_arg(name: &str) -> Result<moonlighter::Affinity, String> {
+	v12::parse_affinity(name).ok_or_else(|| format!("unknown affinity {name}, see --list-affinities"))
+}
+
+fn number_arg(arg: &str, value: &str) -> Result<u64, String> {
+	value.parse().map_err(|error| format!("{arg} needs a number, not {value}: {error}"))
+}
+
+fn print_results(request: &v12::SearchRequest, best: Option<&moonlighter::Recipe>, diagnosis: Option<&v12::Diagnosis>, alternatives: &[v12::Alternative]) -> std::io::Result<()> {
+	let mut out = std::io::stdout().lock();
+	writeln!(out, "{:?} for player number {}", request.options.affinity, request.player_number)?;
+	if let Some(recipe) = best {
+		writeln!(out, "Best recipe found with {} vegetables:", recipe.vegs.len())?;
+		print_recipe(&mut out, recipe)?;
+	} else {
+		writeln!(out, "No recipe for these settings.")?;
+		if let Some(diagnosis) = diagnosis {
+			print_diagnosis(&mut out, request, diagnosis)?;
+		}
+	}
+	for alternative in alternatives.iter().skip(usize::from(best.is_some())) {
+		let run = &alternative.run;
+		writeln!(
+			out,
+			"\nAlternative with {} vegetables, {} cereals{}:",
+			run.vegetables,
+			if run.full_cereals { 4 } else { 1 },
+			if run.complex_processing { ", complex processing" } else { "" }
+		)?;
+		print_recipe(&mut out, &alternative.recipe)?;
+	}
+	Ok(())
+}
+
+fn print_recipe(out: &mut impl std::io::Write, recipe: &moonlighter::Recipe) -> std::io::Result<()> {
+	for step in v12::core::steps(recipe) {
+		writeln!(out, "  {step}")?;
+	}
+	Ok(())
+}
+
+fn print_diagnosis(out: &mut impl std::io::Write, request: &v12::SearchRequest, diagnosis: &v12::Diagnosis) -> std::io::Result<()> {
+	if diagnosis.ruled_out {
+		writeln!(out, "The solver found one, but it needs something excluded or missing.")?;
+	}
+	if let Some(max_fillers) = diagnosis.max_fillers {
+		writeln!(out, "Try --max-fillers {max_fillers}")?;
+	}
+	if let Some(vegetables) = diagnosis.vegetables {
+		writeln!(out, "Try --vegetables {vegetables}")?;
+	}
+	if diagnosis.other_cereals {
+		writeln!(out, "Try --cereals {}", if request.options.full_cereals { 1 } else { 4 })?;
+	}
+	if let Some(extra_offset) = diagnosis.extra_offset {
+		writeln!(out, "Try --custom-offset {}", request.options.custom_offset + extra_offset)?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse_args(args: &[&str]) -> Result<Command, String> {
+		parse(args.iter().map(|arg| (*arg).to_owned()))
+	}
+
+	#[test]
+	fn search_arguments_fill_the_request() {
+		let command = parse_args(&[
+			"--affinity",
+			"Carpentry",
+			"--player-number",
+			"200",
+			"--vegetables",
+			"5",
+			"--cereals",
+			"1",
+			"--complex-processing",
+			"--alternatives",
+			"0",
+			"--format",
+			"json",
+		]);
+		let Ok(Command::Search { request, format }) = command else {
+			panic!("not read as a search");
+		};
+		assert_eq!(request.options.affinity, moonlighter::Affinity::Carpentry);
+		assert_eq!(request.player_number, 62);
+		assert_eq!(request.options.vegetables, 5);
+		assert_eq!(request.options.max_fillers, v12::MAX_FILLERS);
+		assert!(!request.options.full_cereals);
+		assert!(request.options.complex_processing);
+		assert_eq!(request.alternatives, 1);
+		assert_eq!(format, Format::Json);
+		assert!(matches!(parse_args(&["--vegetables", "5", "--help"]), Ok(Command::Help)));
+	}
+
+	#[test]
+	fn bad_numbers_are_errors() {
+		for value in ["many", "-3", "1.5", ""] {
+			let error = parse_args(&["--affinity", "carpentry", "--player-number", "1", "--vegetables", value])
+				.err()
+				.expect("bad number accepted");
+			assert!(error.starts_with("--vegetables needs a number"), "{error}");
+		}
+		assert!(parse_args(&["--affinity", "carpentry", "--player-number", "1", "--cereals", "2"]).is_err());
+		assert_eq!(parse_args(&["--affinity", "carpentry", "--max-fillers"]).err().as_deref(), Some("--max-fillers needs a value"));
+	}
+
+	#[test]
+	fn unknown_affinities_are_errors() {
+		let error = parse_args(&["--affinity", "not an affinity", "--player-number", "1"]).err().expect("unknown affinity accepted");
+		assert_eq!(error, "unknown affinity not an affinity, see --list-affinities");
+		assert!(parse_args(&["--calibration", "nope", "--affinity", "carpentry"]).is_err());
+		assert!(parse_args(&["--player-number", "1"]).is_err());
+		assert!(parse_args(&["--affinity", "carpentry"]).is_err());
+	}
+}
@@ -0,0 +1,155 @@
+//! Results of past searches, so asking again for the same thing is instant.
+
+use std::collections::VecDeque;
+
+use crate::worker;
+
+/// Where the cache is stored, apart from the rest of the app state.
+pub const STORAGE_KEY: &str = "recipe_cache";
+
+/// Results from another version of the app or of moonlighter may not be what the solver gives now.
+/// The build script finds moonlighter's locked commit.
+const VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "+", env!("MOONLIGHTER_REV"));
+
+/// How many results to keep; the least recently used go first.
+const CAPACITY: usize = 200;
+
+/// What a search found.
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub struct Results {
+	pub best: Option<moonlighter::Recipe>,
+	pub alternatives: Vec<worker::Alternative>,
+	pub diagnosis: Option<worker::Diagnosis>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Cache {
+	version: String,
+	/// By search request as JSON, most recently used first.
+	entries: VecDeque<(String, Results)>,
+}
+
+impl Default for Cache {
+	fn default() -> Self {
+		Self {
+			version: VERSION.to_owned(),
+			entries: VecDeque::new(),
+		}
+	}
+}
+
+impl Cache {
+	/// The stored cache, or an empty one if it's missing or from another version.
+	pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+		storage
+			.and_then(|storage| eframe::get_value::<Self>(storage, STORAGE_KEY))
+			.filter(|cache| cache.version == VERSION)
+			.unwrap_or_default()
+	}
+
+	fn key(request: &worker::SearchRequest) -> Option<String> {
+		serde_json::to_string(request).ok()
+	}
+
+	pub fn get(&mut self, request: &worker::SearchRequest) -> Option<Results> {
+		let key = Self::key(request)?;
+		let index = self.entries.iter().position(|(cached, _)| *cached == key)?;
+		let entry = self.entries.remove(index)?;
+		let results = entry.1.clone();
+		self.entries.push_front(entry);
+		Some(results)
+	}
+
+	pub fn insert(&mut self, request: &worker::SearchRequest, results: Results) {
+		let Some(key) = Self::key(request) else {
+			return;
+		};
+		self.entries.retain(|(cached, _)| *cached != key);
+		self.entries.push_front((key, results));
+		self.entries.truncate(CAPACITY);
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	pub fn clear(&mut self) {
+		self.entries.clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::core;
+
+	fn request(player_number: u64) -> worker::SearchRequest {
+		worker::SearchRequest {
+			options: core::Options::default(),
+			player_number,
+			alternatives: 1,
+			inventory: Default::default(),
+			exclusions: Default::default(),
+			candidates: true,
+		}
+	}
+
+	fn results() -> Results {
+		Results {
+			best: Some(core::fixtures::recipe(&[(0, 0)], 0, 2, 0)),
+			alternatives: Vec::new(),
+			diagnosis: None,
+		}
+	}
+
+	#[test]
+	fn the_least_recently_used_go_first() {
+		let mut cache = Cache::default();
+		for player_number in 0..CAPACITY as u64 {
+			cache.insert(&request(player_number), results());
+		}
+		assert_eq!(cache.len(), CAPACITY);
+		// Using the oldest makes the next oldest the one to go.
+		assert!(cache.get(&request(0)).is_some());
+		cache.insert(&request(1000), results());
+		assert_eq!(cache.len(), CAPACITY);
+		assert!(cache.get(&request(0)).is_some());
+		assert!(cache.get(&request(1)).is_none());
+		assert!(cache.get(&request(1000)).is_some());
+		assert!(cache.get(&worker::SearchRequest { candidates: false, ..request(2) }).is_none());
+	}
+
+	#[derive(Default)]
+	struct Memory(std::collections::HashMap<String, String>);
+
+	impl eframe::Storage for Memory {
+		fn get_string(&self, key: &str) -> Option<String> {
+			self.0.get(key).cloned()
+		}
+
+		fn set_string(&mut self, key: &str, value: String) {
+			self.0.insert(key.to_owned(), value);
+		}
+
+		fn flush(&mut self) {}
+	}
+
+	#[test]
+	fn results_from_another_version_are_dropped() {
+		let mut cache = Cache::default();
+		cache.insert(&request(57), results());
+		let mut storage = Memory::default();
+		eframe::set_value(&mut storage, STORAGE_KEY, &cache);
+		assert_eq!(Cache::load(Some(&storage)).len(), 1);
+
+		cache.version = "0.0.0+old".to_owned();
+		eframe::set_value(&mut storage, STORAGE_KEY, &cache);
+		assert!(Cache::load(Some(&storage)).is_empty());
+		assert!(Cache::load(None).is_empty());
+	}
+}
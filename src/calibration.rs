@@ -0,0 +1,125 @@
+//! Working out the player number from a test meal, one step at a time.
+
+use crate::affinity_picker::{self, Picks};
+use crate::core;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+enum Page {
+	#[default]
+	Cook,
+	Read,
+	Verify,
+	Done,
+}
+
+/// The calibration wizard: what to cook, what to read off it, an optional second meal to check the
+/// first, and the player number that comes out.
+#[derive(Default)]
+pub struct Wizard {
+	page: Page,
+	first_meal: Option<moonlighter::Affinity>,
+	second_meal: Option<moonlighter::Affinity>,
+}
+
+pub enum Outcome {
+	Open,
+	Closed,
+	/// The player finished the wizard with this bear meal affinity.
+	Calibrated(moonlighter::Affinity),
+}
+
+impl Wizard {
+	pub fn show(&mut self, ctx: &egui::Context, picks: &mut Picks) -> Outcome {
+		let mut open = true;
+		let mut outcome = Outcome::Open;
+		egui::Window::new("Calibration").open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+			ui.set_max_width(420.0);
+			match self.page {
+				Page::Cook => self.cook_ui(ui),
+				Page::Read => self.read_ui(ui, picks),
+				Page::Verify => self.verify_ui(ui, picks),
+				Page::Done => outcome = self.done_ui(ui),
+			}
+		});
+		if open { outcome } else { Outcome::Closed }
+	}
+
+	fn cook_ui(&mut self, ui: &mut egui::Ui) {
+		ui.heading("1. Cook a test meal");
+		ui.label("Put bear meat and corn in a frying pan and cook it in an ordinary oven.");
+		ui.label("Use plain ingredients and tools: rare ones shift the affinity. The quality doesn't matter.");
+		ui.label("Every character gets a different affinity from this meal, so it tells V12 who you are.");
+		if ui.button("I have it, next").clicked() {
+			self.page = Page::Read;
+		}
+	}
+
+	fn read_ui(&mut self, ui: &mut egui::Ui, picks: &mut Picks) {
+		ui.heading("2. Read its affinity");
+		ui.label("Eat the meal and note which skill it gives you an affinity for. Pick that skill here:");
+		affinity_picker::optional_affinity_picker(ui, "first_meal", "", &mut self.first_meal, picks);
+		if let Some(affinity) = &self.first_meal {
+			ui.label(format!("That makes your player number {}.", core::player_number(affinity)));
+		}
+		ui.horizontal(|ui| {
+			if ui.button("Back").clicked() {
+				self.page = Page::Cook;
+			}
+			if ui.add_enabled(self.first_meal.is_some(), egui::Button::new("Next")).clicked() {
+				self.page = Page::Verify;
+			}
+		});
+	}
+
+	fn verify_ui(&mut self, ui: &mut egui::Ui, picks: &mut Picks) {
+		ui.heading("3. Check it (optional)");
+		ui.label("Picking the wrong skill gives every recipe the wrong affinity. To be sure, cook and eat a second meal the same way: it should give the same skill.");
+		affinity_picker::optional_affinity_picker(ui, "second_meal", "", &mut self.second_meal, picks);
+		let agrees = self.second_meal.is_none() || self.second_meal == self.first_meal;
+		if !agrees {
+			ui.colored_label(
+				ui.visuals().warn_fg_color,
+				"The two meals disagree, so one of them was misread. Cook a third to settle which, and fix the wrong one.",
+			);
+		}
+		ui.horizontal(|ui| {
+			if ui.button("Back").clicked() {
+				self.page = Page::Read;
+			}
+			if self.second_meal.is_none() {
+				if ui.button("Skip").clicked() {
+					self.page = Page::Done;
+				}
+			} else if ui.add_enabled(agrees, egui::Button::new("Next")).clicked() {
+				self.page = Page::Done;
+			}
+		});
+	}
+
+	fn done_ui(&mut self, ui: &mut egui::Ui) -> Outcome {
+		let Some(affinity) = self.first_meal.clone() else {
+			self.page = Page::Read;
+			return Outcome::Open;
+		};
+		let player_number = core::player_number(&affinity);
+		ui.heading(format!("Your player number is {player_number}"));
+		ui.label(format!(
+			"Wurm shifts the affinity of everything you make by a number fixed for your character. The test meal gives {:?} at player number 57, and one affinity further along the list for each number above, so yours gives {affinity:?} at {player_number}.",
+			moonlighter::Affinity::CoalMaking
+		));
+		ui.label("V12 shifts its recipes to make up for it, so they come out with the affinity you ask for.");
+		if self.second_meal.is_some() {
+			ui.label("Both test meals agree.");
+		}
+		let mut outcome = Outcome::Open;
+		ui.horizontal(|ui| {
+			if ui.button("Back").clicked() {
+				self.page = Page::Verify;
+			}
+			if ui.button("Use it").clicked() {
+				outcome = Outcome::Calibrated(affinity.clone());
+			}
+		});
+		outcome
+	}
+}
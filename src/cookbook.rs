@@ -0,0 +1,168 @@
+//! The best recipe for every affinity, for one player number and set of options: a reference chart
+//! to look things up in without searching again.
+
+use crate::{batch, core, variants, worker};
+
+/// How many cookbooks to keep; building one takes a while, so a few are worth holding on to.
+const KEEP: usize = 4;
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct Cookbook {
+	pub player_number: u64,
+	/// The options every recipe was searched with, but for the affinity.
+	pub options: core::Options,
+	pub batch: batch::Batch,
+}
+
+impl Cookbook {
+	/// A cookbook with every affinity queued.
+	fn new(player_number: u64, options: &core::Options) -> Self {
+		let mut batch = batch::Batch::default();
+		for affinity in variants::affinities().iter().filter_map(|name| variants::parse_affinity(name)) {
+			batch.push(worker::SearchRequest {
+				options: core::Options { affinity, ..options.clone() },
+				player_number,
+				alternatives: 1,
+				inventory: Default::default(),
+				exclusions: Default::default(),
+				candidates: false,
+			});
+		}
+		Self {
+			player_number,
+			options: options.clone(),
+			batch,
+		}
+	}
+
+	fn is_for(&self, player_number: u64, options: &core::Options) -> bool {
+		self.player_number == player_number
+			&& self.options
+				== core::Options {
+					affinity: self.options.affinity.clone(),
+					..options.clone()
+				}
+	}
+
+	/// How many affinities are done, out of how many.
+	pub fn progress(&self) -> (usize, usize) {
+		let done = self
+			.batch
+			.entries
+			.iter()
+			.filter(|entry| !matches!(entry.status, batch::Status::Queued | batch::Status::Running | batch::Status::Cancelled))
+			.count();
+		(done, self.batch.entries.len())
+	}
+}
+
+/// The cookbooks built so far, most recently used first.
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Cookbooks {
+	list: Vec<Cookbook>,
+}
+
+impl Cookbooks {
+	pub fn get(&self, player_number: u64, options: &core::Options) -> Option<&Cookbook> {
+		self.list.iter().find(|cookbook| cookbook.is_for(player_number, options))
+	}
+
+	pub fn get_mut(&mut self, player_number: u64, options: &core::Options) -> Option<&mut Cookbook> {
+		self.list.iter_mut().find(|cookbook| cookbook.is_for(player_number, options))
+	}
+
+	/// Starts building the cookbook for these settings, unless there is one already.
+	pub fn build(&mut self, ctx: &egui::Context, player_number: u64, options: &core::Options) {
+		let index = self.list.iter().position(|cookbook| cookbook.is_for(player_number, options));
+		let mut cookbook = match index {
+			Some(index) => self.list.remove(index),
+			None => Cookbook::new(player_number, options),
+		};
+		cookbook.batch.retry_cancelled();
+		cookbook.batch.start(ctx);
+		self.list.insert(0, cookbook);
+		for dropped in self.list.iter_mut().skip(KEEP) {
+			dropped.batch.cancel();
+		}
+		self.list.truncate(KEEP);
+	}
+
+	pub fn remove(&mut self, player_number: u64, options: &core::Options) {
+		for cookbook in &mut self.list {
+			if cookbook.is_for(player_number, options) {
+				cookbook.batch.cancel();
+			}
+		}
+		self.list.retain(|cookbook| !cookbook.is_for(player_number, options));
+	}
+
+	pub fn poll(&mut self, ctx: &egui::Context) {
+		for cookbook in &mut self.list {
+			cookbook.batch.poll(ctx);
+		}
+	}
+
+	/// Cookbooks that were being built when the app was closed are left to be resumed.
+	pub fn restore(&mut self) {
+		for cookbook in &mut self.list {
+			cookbook.batch.restore();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn cookbooks_are_for_any_affinity_but_nothing_else() {
+		let options = core::Options {
+			affinity: moonlighter::Affinity::Carpentry,
+			vegetables: 3,
+			..core::Options::default()
+		};
+		let cookbook = Cookbook::new(57, &options);
+		assert_eq!(cookbook.batch.entries.len(), variants::affinities().len());
+		assert!(cookbook.is_for(57, &options));
+		assert!(cookbook.is_for(
+			57,
+			&core::Options {
+				affinity: moonlighter::Affinity::Archery,
+				..options.clone()
+			}
+		));
+		assert!(!cookbook.is_for(58, &options));
+		assert!(!cookbook.is_for(57, &core::Options { vegetables: 4, ..options.clone() }));
+		assert!(!cookbook.is_for(
+			57,
+			&core::Options {
+				full_cereals: !options.full_cereals,
+				..options
+			}
+		));
+	}
+
+	#[test]
+	fn only_the_most_recent_cookbooks_are_kept() {
+		let ctx = egui::Context::default();
+		let mut cookbooks = Cookbooks::default();
+		for player_number in 0..KEEP as u64 + 2 {
+			cookbooks.build(&ctx, player_number, &core::Options::default());
+			if let Some(cookbook) = cookbooks.list.first_mut() {
+				cookbook.batch.cancel();
+			}
+		}
+		assert_eq!(cookbooks.list.len(), KEEP);
+		assert!(cookbooks.get(0, &core::Options::default()).is_none());
+		assert!(cookbooks.get(1, &core::Options::default()).is_none());
+
+		// Building one again moves it to the front instead of starting over.
+		cookbooks.build(&ctx, 2, &core::Options::default());
+		assert_eq!(cookbooks.list.len(), KEEP);
+		assert_eq!(cookbooks.list.first().map(|cookbook| cookbook.player_number), Some(2));
+		for cookbook in &mut cookbooks.list {
+			cookbook.batch.cancel();
+		}
+	}
+}
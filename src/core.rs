@@ -0,0 +1,578 @@
+//! The model behind every frontend: what the player asks for, who they are, and how a recipe is
+//! laid out as steps to follow.
+
+use crate::variants;
+
+/// What the player asks the solver for. Who they are is the player number, kept apart since it
+/// comes from calibration rather than from the request.
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Options {
+	pub affinity: moonlighter::Affinity,
+	/// Unique vegetables to use.
+	pub vegetables: u64,
+	/// The most sugars and barleys to add.
+	pub max_fillers: u64,
+	/// Allow fried and roasted vegetables.
+	pub complex_processing: bool,
+	/// Use all 4 cereals rather than 1.
+	pub full_cereals: bool,
+	/// +1 for each rare item used, +2 supreme, +3 fantastic.
+	pub custom_offset: u64,
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Self {
+			affinity: moonlighter::Affinity::AggressiveFighting,
+			vegetables: 12,
+			max_fillers: 80,
+			complex_processing: false,
+			full_cereals: true,
+			custom_offset: 0,
+		}
+	}
+}
+
+impl Options {
+	/// What the solver takes, for the given player.
+	pub fn solver_options(&self, player_number: u64) -> moonlighter::Options {
+		moonlighter::Options {
+			affinity: self.affinity.clone(),
+			vegs: self.vegetables,
+			max_fillers: self.max_fillers,
+			complex_processing: self.complex_processing,
+			full_cereals: self.full_cereals,
+			player_number,
+			custom_offset: self.custom_offset,
+		}
+	}
+}
+
+impl std::fmt::Display for Options {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"{:?}, {} vegetables, up to {} fillers, {} cereals",
+			self.affinity,
+			self.vegetables,
+			self.max_fillers,
+			if self.full_cereals { 4 } else { 1 }
+		)?;
+		if self.complex_processing {
+			f.write_str(", complex processing")?;
+		}
+		if self.custom_offset != 0 {
+			write!(f, ", offset +{}", self.custom_offset)?;
+		}
+		Ok(())
+	}
+}
+
+/// A named set of options to generate again later.
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub struct Preset {
+	pub name: String,
+	pub options: Options,
+}
+
+/// The player number that makes bear meat and corn, fried in a pan and cooked in an oven, come out
+/// with the given affinity.
+pub fn player_number(bear_meal_affinity: &moonlighter::Affinity) -> u64 {
+	(138 + 57 + bear_meal_affinity.offset() - moonlighter::Affinity::CoalMaking.offset()) % 138
+}
+
+/// The bear meal affinity that gives the player number, the inverse of [`player_number`].
+pub fn bear_meal_affinity(player_number: u64) -> Option<moonlighter::Affinity> {
+	variants::affinities()
+		.iter()
+		.filter_map(|name| variants::parse_affinity(name))
+		.find(|affinity| self::player_number(affinity) == player_number)
+}
+
+/// Something the player made and saw the affinity of, which pins down their player number.
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum Observation {
+	/// Bear meat and corn, fried in a pan and cooked in an oven.
+	BearMeal { affinity: moonlighter::Affinity },
+	/// Moonshine brewed from a recipe worked out for `player_number` and `target`, that came out
+	/// with `affinity` instead. Every player number off shifts it one affinity along.
+	Brew {
+		player_number: u64,
+		target: moonlighter::Affinity,
+		affinity: moonlighter::Affinity,
+	},
+}
+
+impl Observation {
+	/// The affinity seen.
+	pub fn affinity_mut(&mut self) -> &mut moonlighter::Affinity {
+		match self {
+			Self::BearMeal { affinity } | Self::Brew { affinity, .. } => affinity,
+		}
+	}
+
+	/// The player number this observation alone gives.
+	pub fn player_number(&self) -> u64 {
+		match self {
+			Self::BearMeal { affinity } => player_number(affinity),
+			Self::Brew { player_number, target, affinity } => (player_number % 138 + 138 + affinity.offset() - target.offset()) % 138,
+		}
+	}
+}
+
+/// What a set of observations make of the player number.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CrossCheck {
+	/// The number most observations give, if it's more than any other.
+	pub player_number: Option<u64>,
+	/// Indices of the observations that give some other number, and so were likely misread.
+	pub contradicting: Vec<usize>,
+}
+
+/// Finds the player number the observations agree on, or the one most of them give. An observation
+/// that disagrees with the majority is more likely misread than all the others, so it's flagged.
+pub fn cross_check(observations: &[Observation]) -> CrossCheck {
+	let numbers: Vec<u64> = observations.iter().map(Observation::player_number).collect();
+	let mut counts: std::collections::BTreeMap<u64, usize> = std::collections::BTreeMap::new();
+	for number in &numbers {
+		*counts.entry(*number).or_default() += 1;
+	}
+	let most = counts.values().copied().max().unwrap_or_default();
+	let mut leaders = counts.iter().filter(|(_, count)| **count == most).map(|(number, _)| *number);
+	let player_number = match (leaders.next(), leaders.next()) {
+		(Some(number), None) => Some(number),
+		_ => None,
+	};
+	let contradicting = match player_number {
+		Some(player_number) => numbers.iter().enumerate().filter(|(_, number)| **number != player_number).map(|(index, _)| index).collect(),
+		None if observations.len() > 1 => (0..observations.len()).collect(),
+		None => Vec::new(),
+	};
+	CrossCheck { player_number, contradicting }
+}
+
+/// One thing to put in the still. Ingredients are by variant name.
+#[derive(Clone, PartialEq, Eq, Debug, serde::Deserialize, serde::Serialize)]
+pub enum Step {
+	Water,
+	Sugars(u64),
+	Barleys(u64),
+	Cereal(String),
+	Vegetable { vegetable: String, processing: String },
+}
+
+impl std::fmt::Display for Step {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Water => f.write_str("water"),
+			Self::Sugars(count) => write!(f, "{count} sugars"),
+			Self::Barleys(count) => write!(f, "{count} barleys"),
+			Self::Cereal(cereal) => f.write_str(cereal),
+			Self::Vegetable { vegetable, processing } => write!(f, "{vegetable} {processing}"),
+		}
+	}
+}
+
+/// The recipe as steps, in order: water, sugars, barleys, cereals, then each vegetable with its
+/// processing.
+///
+/// The recipe gives processings as counts in vegetable order, so the first count's worth of
+/// vegetables get the first processing, and so on.
+pub fn steps(recipe: &moonlighter::Recipe) -> Vec<Step> {
+	let processings = recipe.processings.iter().flat_map(|(processing, count)| (0..*count).map(move |_| processing));
+	[Step::Water, Step::Sugars(recipe.sugars), Step::Barleys(recipe.barleys)]
+		.into_iter()
+		.chain(recipe.cereals.iter().map(|cereal| Step::Cereal(variants::name_of(cereal))))
+		.chain(recipe.vegs.iter().zip(processings).map(|(veg, processing)| Step::Vegetable {
+			vegetable: variants::name_of(veg),
+			processing: variants::name_of(processing),
+		}))
+		.collect()
+}
+
+/// The recipe with these steps, the other way round from [`steps`]. Names are matched loosely.
+///
+/// # Errors
+///
+/// If a name isn't one of moonlighter's.
+pub fn recipe(steps: &[Step]) -> Result<moonlighter::Recipe, String> {
+	let (mut sugars, mut barleys) = (0, 0);
+	let mut cereals = Vec::new();
+	let mut vegetables = Vec::new();
+	let mut processings: Vec<(&str, u64)> = Vec::new();
+	for step in steps {
+		match step {
+			Step::Water => {}
+			Step::Sugars(count) => sugars += count,
+			Step::Barleys(count) => barleys += count,
+			Step::Cereal(cereal) => cereals.push(variants::find(variants::cereals(), cereal).ok_or_else(|| format!("Unknown cereal {cereal}"))?),
+			Step::Vegetable { vegetable, processing } => {
+				vegetables.push(variants::find(variants::vegetables(), vegetable).ok_or_else(|| format!("Unknown vegetable {vegetable}"))?);
+				let processing = variants::find(variants::processings(), processing).ok_or_else(|| format!("Unknown processing {processing}"))?;
+				match processings.last_mut() {
+					Some((last, count)) if *last == processing => *count += 1,
+					_ => processings.push((processing, 1)),
+				}
+			}
+		}
+	}
+	serde_json::from_value(serde_json::json!({
+		"vegs": vegetables,
+		"processings": processings,
+		"cereals": cereals,
+		"sugars": sugars,
+		"barleys": barleys,
+	}))
+	.map_err(|error| format!("Not a recipe: {error}"))
+}
+
+/// A step of a recipe being brewed, and whether it's been done.
+#[derive(Clone, PartialEq, Eq, Debug, serde::Deserialize, serde::Serialize)]
+pub struct ChecklistItem {
+	pub step: Step,
+	pub done: bool,
+}
+
+/// The recipe's steps to tick off. If `previous` is the checklist of a recipe with the same steps,
+/// what was ticked off there stays ticked.
+pub fn checklist(recipe: &moonlighter::Recipe, previous: &[ChecklistItem]) -> Vec<ChecklistItem> {
+	let steps = steps(recipe);
+	if steps.len() == previous.len() && steps.iter().zip(previous).all(|(step, item)| *step == item.step) {
+		return previous.to_vec();
+	}
+	steps.into_iter().map(|step| ChecklistItem { step, done: false }).collect()
+}
+
+/// One numbered instruction of the guided brewing view.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Instruction {
+	pub text: String,
+	/// Indices into [`steps`] of the steps this instruction covers, to tick off once it's done.
+	pub steps: Vec<usize>,
+}
+
+/// The recipe as instructions in the order to follow in game: prepare each vegetable, put them in
+/// with the cereals, water, sugars and barleys, then ferment and distil.
+pub fn instructions(recipe: &moonlighter::Recipe) -> Vec<Instruction> {
+	let instruction = |text: String, steps: Vec<usize>| Instruction { text, steps };
+	let steps = steps(recipe);
+	let mut instructions = Vec::new();
+	let mut vegetables = Vec::new();
+	let mut cereals = Vec::new();
+	let mut fillers = Vec::new();
+	// Zero sugars or barleys is nothing to do, so it's done along with the water.
+	let mut nothing_to_add = Vec::new();
+	for (index, step) in steps.iter().enumerate() {
+		match step {
+			Step::Vegetable { vegetable, processing } => {
+				instructions.push(instruction(format!("Prepare {vegetable}: {}.", processing.to_lowercase()), Vec::new()));
+				vegetables.push(index);
+			}
+			Step::Cereal(cereal) => cereals.push((index, cereal.as_str())),
+			Step::Water => fillers.push(instruction("Add water.".to_owned(), vec![index])),
+			Step::Sugars(0) | Step::Barleys(0) => nothing_to_add.push(index),
+			Step::Sugars(count) => fillers.push(instruction(format!("Add {count} sugar."), vec![index])),
+			Step::Barleys(count) => fillers.push(instruction(format!("Add {count} barley."), vec![index])),
+		}
+	}
+	if let Some(water) = fillers.first_mut() {
+		water.steps.extend(nothing_to_add);
+	}
+	let names: Vec<&str> = cereals.iter().map(|(_, cereal)| *cereal).collect();
+	let text = if names.is_empty() {
+		"Put the prepared vegetables in the container.".to_owned()
+	} else {
+		format!("Put the prepared vegetables in the container, with {}.", names.join(", "))
+	};
+	instructions.push(instruction(text, vegetables.into_iter().chain(cereals.iter().map(|(index, _)| *index)).collect()));
+	instructions.extend(fillers);
+	instructions.push(instruction("Leave it to ferment.".to_owned(), Vec::new()));
+	instructions.push(instruction("Distil it.".to_owned(), Vec::new()));
+	instructions
+}
+
+/// The recipe as a few short lines to paste in chat: what it's for, then the fillers, the cereals,
+/// and the vegetables grouped by processing.
+pub fn chat_text(recipe: &moonlighter::Recipe, options: &Options, player_number: u64) -> String {
+	let mut heading = format!("{:?} moonshine, player number {player_number}", options.affinity);
+	if options.custom_offset > 0 {
+		heading += &format!(", offset +{}", options.custom_offset);
+	}
+	let mut lines = vec![heading, format!("Water, {} sugars, {} barleys", recipe.sugars, recipe.barleys)];
+	let mut cereals = Vec::new();
+	let mut processings: Vec<(String, Vec<String>)> = Vec::new();
+	for step in steps(recipe) {
+		match step {
+			Step::Cereal(cereal) => cereals.push(cereal),
+			Step::Vegetable { vegetable, processing } => match processings.iter_mut().find(|(name, _)| *name == processing) {
+				Some((_, vegetables)) => vegetables.push(vegetable),
+				None => processings.push((processing, vec![vegetable])),
+			},
+			Step::Water | Step::Sugars(_) | Step::Barleys(_) => {}
+		}
+	}
+	if !cereals.is_empty() {
+		lines.push(format!("Cereals: {}", cereals.join(", ")));
+	}
+	lines.extend(processings.into_iter().map(|(processing, vegetables)| format!("{processing}: {}", vegetables.join(", "))));
+	lines.join("\n")
+}
+
+/// Ingredients and recipes for tests, by index into moonlighter's lists of variants.
+#[cfg(test)]
+pub mod fixtures {
+	use crate::variants;
+
+	pub fn vegetable(index: usize) -> &'static str {
+		variants::vegetables().get(index).copied().expect("too few vegetables")
+	}
+
+	pub fn processing(index: usize) -> &'static str {
+		variants::processings().get(index).copied().expect("too few processings")
+	}
+
+	pub fn cereal(index: usize) -> &'static str {
+		variants::cereals().get(index).copied().expect("too few cereals")
+	}
+
+	/// A recipe with these vegetables, each with its processing, the first `cereals` cereals, and
+	/// the sugars and barleys.
+	pub fn recipe(vegetables: &[(usize, usize)], cereals: usize, sugars: u64, barleys: u64) -> moonlighter::Recipe {
+		let mut processings: Vec<(&str, u64)> = Vec::new();
+		for &(_, index) in vegetables {
+			match processings.last_mut() {
+				Some((last, count)) if *last == processing(index) => *count += 1,
+				_ => processings.push((processing(index), 1)),
+			}
+		}
+		serde_json::from_value(serde_json::json!({
+			"vegs": vegetables.iter().map(|&(index, _)| vegetable(index)).collect::<Vec<_>>(),
+			"processings": processings,
+			"cereals": (0..cereals).map(cereal).collect::<Vec<_>>(),
+			"sugars": sugars,
+			"barleys": barleys,
+		}))
+		.expect("not a recipe")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::fixtures::{cereal, processing, vegetable};
+	use super::*;
+
+	#[test]
+	fn coal_making_calibration_is_player_57() {
+		assert_eq!(player_number(&moonlighter::Affinity::CoalMaking), 57);
+	}
+
+	#[test]
+	fn player_numbers_cover_all_offsets() {
+		let mut numbers: Vec<u64> = variants::affinities()
+			.iter()
+			.filter_map(|name| variants::parse_affinity(name))
+			.map(|affinity| player_number(&affinity))
+			.collect();
+		numbers.sort_unstable();
+		numbers.dedup();
+		assert_eq!(numbers, (0..138).collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn bear_meal_affinity_inverts_player_number() {
+		for number in [0, 57, 137] {
+			let affinity = bear_meal_affinity(number).expect("no affinity for player number");
+			assert_eq!(player_number(&affinity), number);
+		}
+	}
+
+	#[test]
+	fn brew_off_by_one_affinity_is_off_by_one_player_number() {
+		let [target, next] = [0, 1].map(|offset| {
+			variants::affinities()
+				.iter()
+				.filter_map(|name| variants::parse_affinity(name))
+				.find(|affinity| affinity.offset() == offset)
+				.expect("no affinity at offset")
+		});
+		let exact = Observation::Brew {
+			player_number: 10,
+			target: target.clone(),
+			affinity: target.clone(),
+		};
+		assert_eq!(exact.player_number(), 10);
+		let ahead = Observation::Brew {
+			player_number: 10,
+			target: target.clone(),
+			affinity: next.clone(),
+		};
+		assert_eq!(ahead.player_number(), 11);
+		let behind = Observation::Brew {
+			player_number: 0,
+			target: next,
+			affinity: target,
+		};
+		assert_eq!(behind.player_number(), 137);
+	}
+
+	#[test]
+	fn cross_check_flags_the_odd_one_out() {
+		let meal = |number| Observation::BearMeal {
+			affinity: bear_meal_affinity(number).expect("no affinity for player number"),
+		};
+		let check = cross_check(&[meal(12), meal(13), meal(12)]);
+		assert_eq!(check.player_number, Some(12));
+		assert_eq!(check.contradicting, [1]);
+
+		let check = cross_check(&[meal(12), meal(13)]);
+		assert_eq!(check.player_number, None);
+		assert_eq!(check.contradicting, [0, 1]);
+
+		assert_eq!(
+			cross_check(&[meal(5)]),
+			CrossCheck {
+				player_number: Some(5),
+				contradicting: Vec::new()
+			}
+		);
+		assert_eq!(
+			cross_check(&[]),
+			CrossCheck {
+				player_number: None,
+				contradicting: Vec::new()
+			}
+		);
+	}
+
+	#[test]
+	fn solver_options_carry_player_number() {
+		let options = Options {
+			custom_offset: 2,
+			..Options::default()
+		};
+		let solver = options.solver_options(42);
+		assert_eq!(solver.player_number, 42);
+		assert_eq!(solver.vegs, options.vegetables);
+		assert_eq!(solver.custom_offset, 2);
+		assert_eq!(solver.affinity, options.affinity);
+	}
+
+	#[test]
+	fn steps_pair_vegetables_with_processings_in_order() {
+		let [first, second, third] = [0, 1, 2].map(vegetable);
+		let [raw, other] = [0, 1].map(processing);
+		// Written out rather than from the fixture, for the processing with a count of zero.
+		let recipe: moonlighter::Recipe = serde_json::from_value(serde_json::json!({
+			"vegs": [first, second, third],
+			"processings": [[raw, 2], [other, 0], [other, 1]],
+			"cereals": [cereal(0)],
+			"sugars": 3,
+			"barleys": 0,
+		}))
+		.expect("not a recipe");
+
+		let step = |vegetable: &str, processing: &str| Step::Vegetable {
+			vegetable: vegetable.to_owned(),
+			processing: processing.to_owned(),
+		};
+		assert_eq!(
+			steps(&recipe),
+			[
+				Step::Water,
+				Step::Sugars(3),
+				Step::Barleys(0),
+				Step::Cereal(cereal(0).to_owned()),
+				step(first, raw),
+				step(second, raw),
+				step(third, other)
+			]
+		);
+	}
+
+	#[test]
+	fn recipe_from_steps_gives_the_same_steps() {
+		let original = fixtures::recipe(&[(0, 0), (1, 1), (2, 0)], variants::cereals().len(), 2, 5);
+
+		let steps = steps(&original);
+		assert_eq!(recipe(&steps).map(|rebuilt| super::steps(&rebuilt)), Ok(steps));
+		assert!(recipe(&[Step::Cereal("Nothing".to_owned())]).is_err());
+	}
+
+	#[test]
+	fn checklist_keeps_progress_only_for_the_same_recipe() {
+		let with = |vegetable: usize, sugars: u64| fixtures::recipe(&[(vegetable, 0)], 0, sugars, sugars);
+
+		let mut ticked = checklist(&with(0, 2), &[]);
+		assert!(ticked.iter().all(|item| !item.done));
+		for item in &mut ticked {
+			item.done = true;
+		}
+		assert!(checklist(&with(0, 2), &ticked).iter().all(|item| item.done));
+		assert!(checklist(&with(1, 2), &ticked).iter().all(|item| !item.done));
+		assert!(checklist(&with(0, 3), &ticked).iter().all(|item| !item.done));
+	}
+
+	#[test]
+	fn sugars_and_barleys_are_separate_steps() {
+		let mut items = checklist(&fixtures::recipe(&[], 0, 1, 1), &[]);
+		if let Some(sugars) = items.iter_mut().find(|item| item.step == Step::Sugars(1)) {
+			sugars.done = true;
+		}
+		assert_eq!(items.iter().find(|item| item.step == Step::Barleys(1)).map(|item| item.done), Some(false));
+	}
+
+	#[test]
+	fn instructions_cover_every_step_once_in_brewing_order() {
+		let recipe = fixtures::recipe(&[(0, 0), (1, 0)], 1, 2, 0);
+
+		let instructions = instructions(&recipe);
+		let texts: Vec<&str> = instructions.iter().map(|instruction| instruction.text.as_str()).collect();
+		let raw = processing(0).to_lowercase();
+		assert_eq!(
+			texts,
+			[
+				format!("Prepare {}: {raw}.", vegetable(0)),
+				format!("Prepare {}: {raw}.", vegetable(1)),
+				format!("Put the prepared vegetables in the container, with {}.", cereal(0)),
+				"Add water.".to_owned(),
+				"Add 2 sugar.".to_owned(),
+				"Leave it to ferment.".to_owned(),
+				"Distil it.".to_owned(),
+			]
+		);
+
+		let mut covered: Vec<usize> = instructions.iter().flat_map(|instruction| instruction.steps.iter().copied()).collect();
+		covered.sort_unstable();
+		assert_eq!(covered, (0..steps(&recipe).len()).collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn chat_text_groups_vegetables_by_processing() {
+		let [first, second, third] = [0, 1, 2].map(vegetable);
+		let [raw, other] = [0, 1].map(processing);
+		let cereal = cereal(0);
+		let recipe = fixtures::recipe(&[(0, 0), (1, 1), (2, 0)], 1, 3, 1);
+		let options = Options {
+			affinity: moonlighter::Affinity::CoalMaking,
+			custom_offset: 2,
+			..Options::default()
+		};
+
+		assert_eq!(
+			chat_text(&recipe, &options, 57),
+			format!("CoalMaking moonshine, player number 57, offset +2\nWater, 3 sugars, 1 barleys\nCereals: {cereal}\n{raw}: {first}, {third}\n{other}: {second}")
+		);
+	}
+
+	#[test]
+	fn steps_display_like_the_checklist() {
+		assert_eq!(Step::Water.to_string(), "water");
+		assert_eq!(Step::Sugars(4).to_string(), "4 sugars");
+		let step = Step::Vegetable {
+			vegetable: "Potato".to_owned(),
+			processing: "Chopped".to_owned(),
+		};
+		assert_eq!(step.to_string(), "Potato Chopped");
+	}
+}
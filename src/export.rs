@@ -0,0 +1,156 @@
+//! Recipes as files for spreadsheets and scripts: JSON, or CSV with one row per setting and
+//! ingredient, and reading them back.
+
+use crate::{core, variants};
+
+/// A recipe with what it was generated for.
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub struct SavedRecipe {
+	pub options: core::Options,
+	pub player_number: u64,
+	pub recipe: moonlighter::Recipe,
+}
+
+pub const CSV_HEADER: &str = "field,value,processing";
+
+impl SavedRecipe {
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(self).unwrap_or_default()
+	}
+
+	/// # Errors
+	///
+	/// If the text isn't a recipe saved as JSON.
+	pub fn from_json(text: &str) -> Result<Self, String> {
+		serde_json::from_str(text).map_err(|error| format!("Not a recipe: {error}"))
+	}
+
+	/// The settings first, then the sugars, barleys and cereals, then each vegetable with its
+	/// processing, in recipe order.
+	pub fn to_csv(&self) -> String {
+		let options = &self.options;
+		let mut rows = vec![
+			CSV_HEADER.to_owned(),
+			format!("affinity,{},", variants::name_of(&options.affinity)),
+			format!("player_number,{},", self.player_number),
+			format!("vegetables,{},", options.vegetables),
+			format!("max_fillers,{},", options.max_fillers),
+			format!("complex_processing,{},", options.complex_processing),
+			format!("full_cereals,{},", options.full_cereals),
+			format!("custom_offset,{},", options.custom_offset),
+		];
+		for step in core::steps(&self.recipe) {
+			rows.push(match step {
+				core::Step::Water => continue,
+				core::Step::Sugars(count) => format!("sugars,{count},"),
+				core::Step::Barleys(count) => format!("barleys,{count},"),
+				core::Step::Cereal(cereal) => format!("cereal,{cereal},"),
+				core::Step::Vegetable { vegetable, processing } => format!("vegetable,{vegetable},{processing}"),
+			});
+		}
+		let mut csv = rows.join("\n");
+		csv.push('\n');
+		csv
+	}
+
+	/// Reads what [`Self::to_csv`] writes. Settings that are left out keep their defaults; names are
+	/// matched loosely, and the columns may be quoted.
+	///
+	/// # Errors
+	///
+	/// With the first line that can't be read, or if there's no player number.
+	pub fn from_csv(text: &str) -> Result<Self, String> {
+		let mut options = core::Options::default();
+		let mut player_number = None;
+		let mut steps = Vec::new();
+		for (number, line) in text.lines().enumerate() {
+			let columns: Vec<&str> = line.split(',').map(|column| column.trim().trim_matches('"').trim()).collect();
+			let (field, value, processing) = match columns.as_slice() {
+				[] | [""] => continue,
+				[field, value] => (*field, *value, ""),
+				[field, value, processing, ..] => (*field, *value, *processing),
+				[field] => return Err(format!("Line {}: no value for {field}", number + 1)),
+			};
+			let number_value = || value.parse::<u64>().map_err(|error| format!("Line {}: {field} needs a number, not {value}: {error}", number + 1));
+			let flag_value = || value.parse::<bool>().map_err(|error| format!("Line {}: {field} needs true or false, not {value}: {error}", number + 1));
+			let name = |names: &[&'static str], value: &str| variants::find(names, value).map(str::to_owned).ok_or_else(|| format!("Line {}: unknown {field} {value}", number + 1));
+			match field {
+				"field" if number == 0 => {}
+				"affinity" => options.affinity = variants::parse_affinity(value).ok_or_else(|| format!("Line {}: unknown affinity {value}", number + 1))?,
+				"player_number" => player_number = Some(number_value()? % 138),
+				"vegetables" => options.vegetables = number_value()?,
+				"max_fillers" => options.max_fillers = number_value()?,
+				"complex_processing" => options.complex_processing = flag_value()?,
+				"full_cereals" => options.full_cereals = flag_value()?,
+				"custom_offset" => options.custom_offset = number_value()?,
+				"sugars" => steps.push(core::Step::Sugars(number_value()?)),
+				"barleys" => steps.push(core::Step::Barleys(number_value()?)),
+				"cereal" => steps.push(core::Step::Cereal(name(variants::cereals(), value)?)),
+				"vegetable" => steps.push(core::Step::Vegetable {
+					vegetable: name(variants::vegetables(), value)?,
+					processing: name(variants::processings(), processing)?,
+				}),
+				other => return Err(format!("Line {}: unknown field {other}", number + 1)),
+			}
+		}
+		let recipe = core::recipe(&steps)?;
+		Ok(Self {
+			options,
+			player_number: player_number.ok_or("No player_number")?,
+			recipe,
+		})
+	}
+
+	/// Reads either format, telling them apart by the first character.
+	///
+	/// # Errors
+	///
+	/// As [`Self::from_json`] or [`Self::from_csv`].
+	pub fn import(text: &str) -> Result<Self, String> {
+		if text.trim_start().starts_with('{') { Self::from_json(text) } else { Self::from_csv(text) }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn saved() -> SavedRecipe {
+		SavedRecipe {
+			options: core::Options {
+				affinity: moonlighter::Affinity::CoalMaking,
+				vegetables: 3,
+				custom_offset: 1,
+				..core::Options::default()
+			},
+			player_number: 57,
+			recipe: core::fixtures::recipe(&[(0, 0), (1, 0), (2, 1)], 1, 3, 1),
+		}
+	}
+
+	fn assert_same(imported: &SavedRecipe, saved: &SavedRecipe) {
+		assert!(imported.options == saved.options);
+		assert_eq!(imported.player_number, saved.player_number);
+		assert_eq!(core::steps(&imported.recipe), core::steps(&saved.recipe));
+	}
+
+	#[test]
+	fn json_reads_back() {
+		let saved = saved();
+		assert_same(&SavedRecipe::import(&saved.to_json()).expect("JSON not read"), &saved);
+	}
+
+	#[test]
+	fn csv_reads_back() {
+		let saved = saved();
+		let csv = saved.to_csv();
+		assert!(csv.starts_with(CSV_HEADER));
+		assert_same(&SavedRecipe::import(&csv).expect("CSV not read"), &saved);
+	}
+
+	#[test]
+	fn csv_reports_the_line_it_cannot_read() {
+		let csv = format!("{CSV_HEADER}\nplayer_number,57,\nvegetable,Nothing,Raw\n");
+		assert_eq!(SavedRecipe::from_csv(&csv).err().as_deref(), Some("Line 3: unknown vegetable Nothing"));
+	}
+}
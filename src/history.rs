@@ -0,0 +1,124 @@
+//! Recipes generated before, with the settings they were generated for, to come back to later.
+
+use crate::core;
+
+/// How many recipes to keep; the oldest go first.
+const CAPACITY: usize = 100;
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub struct Entry {
+	pub options: core::Options,
+	pub player_number: u64,
+	/// When it was generated, in seconds since the Unix epoch.
+	pub timestamp: u64,
+	pub recipe: moonlighter::Recipe,
+	/// Whether `recipe` is the solver's answer for `options`, rather than one for nearby settings.
+	pub optimal: bool,
+}
+
+/// Most recent first.
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct History {
+	pub entries: Vec<Entry>,
+}
+
+impl History {
+	/// Records a recipe. Generating the same thing again only moves it back to the top.
+	pub fn record(&mut self, options: &core::Options, player_number: u64, recipe: &moonlighter::Recipe, optimal: bool) {
+		let steps = core::steps(recipe);
+		self.entries
+			.retain(|entry| !(entry.options == *options && entry.player_number == player_number && core::steps(&entry.recipe) == steps));
+		self.entries.insert(
+			0,
+			Entry {
+				options: options.clone(),
+				player_number,
+				timestamp: now(),
+				recipe: recipe.clone(),
+				optimal,
+			},
+		);
+		self.entries.truncate(CAPACITY);
+	}
+
+	pub fn remove(&mut self, index: usize) {
+		if index < self.entries.len() {
+			self.entries.remove(index);
+		}
+	}
+}
+
+/// Seconds since the Unix epoch. `SystemTime` isn't available in the browser.
+pub fn now() -> u64 {
+	#[cfg(target_arch = "wasm32")]
+	{
+		(js_sys::Date::now() / 1000.0) as u64
+	}
+	#[cfg(not(target_arch = "wasm32"))]
+	{
+		std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+	}
+}
+
+/// How long ago a timestamp was, like `5 minutes ago`.
+pub fn age(timestamp: u64, now: u64) -> String {
+	let seconds = now.saturating_sub(timestamp);
+	let (count, unit) = match seconds {
+		0..60 => return "just now".to_owned(),
+		60..3_600 => (seconds / 60, "minute"),
+		3_600..86_400 => (seconds / 3_600, "hour"),
+		_ => (seconds / 86_400, "day"),
+	};
+	format!("{count} {unit}{} ago", if count == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn recording_again_moves_the_recipe_to_the_top() {
+		let mut history = History::default();
+		let options = core::Options::default();
+		let first = core::fixtures::recipe(&[(0, 0)], 0, 2, 0);
+		let second = core::fixtures::recipe(&[(1, 1)], 0, 2, 0);
+		history.record(&options, 57, &first, true);
+		history.record(&options, 57, &second, true);
+		history.record(&options, 57, &first, false);
+		assert_eq!(
+			history.entries.iter().map(|entry| core::steps(&entry.recipe)).collect::<Vec<_>>(),
+			[core::steps(&first), core::steps(&second)]
+		);
+		assert!(history.entries.first().is_some_and(|entry| !entry.optimal));
+
+		// The same recipe for someone else, or other settings, is another entry.
+		history.record(&options, 58, &first, true);
+		history.record(&core::Options { vegetables: 3, ..options }, 57, &first, true);
+		assert_eq!(history.entries.len(), 4);
+	}
+
+	#[test]
+	fn only_the_newest_are_kept() {
+		let mut history = History::default();
+		let recipe = core::fixtures::recipe(&[(0, 0)], 0, 2, 0);
+		for player_number in 0..CAPACITY as u64 + 5 {
+			history.record(&core::Options::default(), player_number, &recipe, true);
+		}
+		assert_eq!(history.entries.len(), CAPACITY);
+		assert_eq!(history.entries.first().map(|entry| entry.player_number), Some(CAPACITY as u64 + 4));
+		assert_eq!(history.entries.last().map(|entry| entry.player_number), Some(5));
+	}
+
+	#[test]
+	fn ages_read_naturally() {
+		assert_eq!(age(1_000, 1_000), "just now");
+		assert_eq!(age(1_000, 1_059), "just now");
+		assert_eq!(age(1_000, 1_060), "1 minute ago");
+		assert_eq!(age(1_000, 1_000 + 3_599), "59 minutes ago");
+		assert_eq!(age(1_000, 1_000 + 7_200), "2 hours ago");
+		assert_eq!(age(1_000, 1_000 + 86_400), "1 day ago");
+		// A clock set back shows the timestamp as just made.
+		assert_eq!(age(1_000, 10), "just now");
+	}
+}
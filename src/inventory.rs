@@ -0,0 +1,174 @@
+//! What recipes may use: what the player has on hand, and what they've ruled out.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::variants;
+
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Inventory {
+	/// Only accept recipes the inventory can make.
+	pub enabled: bool,
+	/// How many of each vegetable are on hand, by name. Missing means none.
+	pub vegetables: BTreeMap<String, u64>,
+	/// Processings that can't be done, for lack of an oven or the like, by name.
+	pub missing_processings: BTreeSet<String>,
+}
+
+impl Inventory {
+	/// How many times the recipe can be made from what is on hand; zero if it needs something missing.
+	pub fn batches(&self, recipe: &moonlighter::Recipe) -> u64 {
+		if processing_names(recipe).any(|processing| self.missing_processings.contains(&processing)) {
+			return 0;
+		}
+
+		let mut needed: BTreeMap<String, u64> = BTreeMap::new();
+		for veg in &recipe.vegs {
+			*needed.entry(variants::name_of(veg)).or_default() += 1;
+		}
+		needed
+			.iter()
+			.map(|(veg, count)| self.vegetables.get(veg).copied().unwrap_or_default() / count)
+			.min()
+			.unwrap_or(u64::MAX)
+	}
+
+	/// Whether the search may use this recipe.
+	pub fn allows(&self, recipe: &moonlighter::Recipe) -> bool {
+		!self.enabled || self.batches(recipe) > 0
+	}
+
+	/// Sets vegetable counts from lines like `Potato 12` or `carrot: 3`, matching names loosely.
+	/// Returns the lines it couldn't make sense of.
+	pub fn paste(&mut self, text: &str) -> Vec<String> {
+		let mut unread = Vec::new();
+		for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+			let parsed = line.rsplit_once(|c: char| c.is_whitespace() || c == ':' || c == ',' || c == '=').and_then(|(name, count)| {
+				let name = variants::normalize(name);
+				let veg = variants::vegetables().iter().find(|veg| variants::normalize(veg) == name)?;
+				Some((*veg, count.trim().parse::<u64>().ok()?))
+			});
+			match parsed {
+				Some((veg, count)) => {
+					self.vegetables.insert(veg.to_owned(), count);
+				}
+				None => unread.push(line.to_owned()),
+			}
+		}
+		unread
+	}
+}
+
+/// Vegetables and processings not to use, whatever the inventory says.
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Exclusions {
+	pub vegetables: BTreeSet<String>,
+	pub processings: BTreeSet<String>,
+}
+
+impl Exclusions {
+	pub fn is_empty(&self) -> bool {
+		self.vegetables.is_empty() && self.processings.is_empty()
+	}
+
+	pub fn allows(&self, recipe: &moonlighter::Recipe) -> bool {
+		!recipe.vegs.iter().any(|veg| self.vegetables.contains(&variants::name_of(veg))) && !processing_names(recipe).any(|processing| self.processings.contains(&processing))
+	}
+}
+
+/// Names of the processings a recipe actually uses.
+pub fn processing_names(recipe: &moonlighter::Recipe) -> impl Iterator<Item = String> {
+	recipe.processings.iter().filter(|(_, count)| *count != 0).map(|(processing, _)| variants::name_of(processing))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn vegetable(index: usize) -> &'static str {
+		variants::vegetables().get(index).copied().expect("too few vegetables")
+	}
+
+	fn processing(index: usize) -> &'static str {
+		variants::processings().get(index).copied().expect("too few processings")
+	}
+
+	fn recipe(vegetables: &[&str], processings: &[(&str, u64)]) -> moonlighter::Recipe {
+		serde_json::from_value(serde_json::json!({
+			"vegs": vegetables,
+			"processings": processings,
+			"cereals": [],
+			"sugars": 0,
+			"barleys": 0,
+		}))
+		.expect("not a recipe")
+	}
+
+	#[test]
+	fn paste_reads_the_usual_formats() {
+		let mut inventory = Inventory::default();
+		let text = format!("{} 12\n  {}: 3\n{},7\n{} = 0\n", vegetable(0), vegetable(1).to_lowercase(), vegetable(2).to_uppercase(), vegetable(3));
+		assert!(inventory.paste(&text).is_empty());
+		assert_eq!(inventory.vegetables.get(vegetable(0)), Some(&12));
+		assert_eq!(inventory.vegetables.get(vegetable(1)), Some(&3));
+		assert_eq!(inventory.vegetables.get(vegetable(2)), Some(&7));
+		assert_eq!(inventory.vegetables.get(vegetable(3)), Some(&0));
+	}
+
+	#[test]
+	fn paste_returns_the_lines_it_cant_read() {
+		let mut inventory = Inventory::default();
+		let text = format!("Moonbeans 4\n{} lots\n{}\n\n{} 2", vegetable(0), vegetable(1), vegetable(2));
+		assert_eq!(inventory.paste(&text), ["Moonbeans 4".to_owned(), format!("{} lots", vegetable(0)), vegetable(1).to_owned()]);
+		assert_eq!(inventory.vegetables.len(), 1);
+	}
+
+	#[test]
+	fn batches_are_limited_by_the_scarcest_vegetable() {
+		let inventory = Inventory {
+			enabled: true,
+			vegetables: [(vegetable(0).to_owned(), 7), (vegetable(1).to_owned(), 2), (vegetable(2).to_owned(), 0)].into(),
+			..Inventory::default()
+		};
+		let raw = [(processing(0), 3)];
+		assert_eq!(inventory.batches(&recipe(&[vegetable(0), vegetable(0), vegetable(1)], &raw)), 2);
+		assert_eq!(inventory.batches(&recipe(&[vegetable(0), vegetable(2)], &raw)), 0);
+		assert!(!inventory.allows(&recipe(&[vegetable(0), vegetable(3)], &raw)));
+		assert!(inventory.allows(&recipe(&[vegetable(0)], &raw)));
+	}
+
+	#[test]
+	fn missing_processings_rule_recipes_out() {
+		let mut inventory = Inventory {
+			enabled: true,
+			vegetables: [(vegetable(0).to_owned(), 5)].into(),
+			missing_processings: [processing(1).to_owned()].into(),
+		};
+		assert!(inventory.allows(&recipe(&[vegetable(0)], &[(processing(0), 1), (processing(1), 0)])));
+		assert!(!inventory.allows(&recipe(&[vegetable(0)], &[(processing(1), 1)])));
+		inventory.enabled = false;
+		assert!(inventory.allows(&recipe(&[vegetable(0)], &[(processing(1), 1)])));
+	}
+
+	#[test]
+	fn exclusions_filter_vegetables_and_used_processings() {
+		let mut exclusions = Exclusions::default();
+		assert!(exclusions.is_empty());
+		let mixed = recipe(&[vegetable(0), vegetable(1)], &[(processing(0), 2), (processing(1), 0)]);
+		assert!(exclusions.allows(&mixed));
+
+		exclusions.processings.insert(processing(1).to_owned());
+		assert!(!exclusions.is_empty());
+		assert!(exclusions.allows(&mixed), "an unused processing ruled the recipe out");
+		exclusions.processings.insert(processing(0).to_owned());
+		assert!(!exclusions.allows(&mixed));
+
+		let exclusions = Exclusions {
+			vegetables: [vegetable(1).to_owned()].into(),
+			..Exclusions::default()
+		};
+		assert!(!exclusions.allows(&mixed));
+		assert!(exclusions.allows(&recipe(&[vegetable(0)], &[(processing(0), 1)])));
+	}
+}
@@ -1,4 +1,25 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+mod affinity_picker;
 mod app;
+mod batch;
+mod cache;
+mod calibration;
+mod cookbook;
+pub mod core;
+pub mod export;
+mod history;
+mod inventory;
+mod link;
+mod profiles;
+mod share;
+mod variants;
+mod worker;
 pub use app::TemplateApp;
+pub use inventory::{Exclusions, Inventory};
+#[cfg(target_arch = "wasm32")]
+pub use link::read as read_link;
+pub use variants::{affinities, parse_affinity};
+#[cfg(target_arch = "wasm32")]
+pub use worker::run_web_worker;
+pub use worker::{Alternative, CancelToken, Diagnosis, MAX_FILLERS, Message, SearchRequest, search};
@@ -0,0 +1,161 @@
+//! Settings, and the recipe if there is one, in the part of the web page's address after the `#`,
+//! so a link or bookmark opens the app on them.
+//!
+//! The fragment is `key=value` pairs joined by `&`, with the keys named after the command line
+//! options, like `affinity=Carpentry&vegetables=10&player-number=57`. A recipe adds `sugars`,
+//! `barleys`, `recipe-cereals` as a list, and `vegs` as a list of `vegetable:processing`.
+
+use crate::{core, variants, worker};
+
+/// Where the web build is published, for links copied from any build.
+pub const WEB_APP: &str = "https://zink-stake.github.io/v12/";
+
+#[derive(Clone)]
+pub struct Link {
+	pub options: core::Options,
+	/// Who the recipe was generated for.
+	pub player_number: Option<u64>,
+	pub recipe: Option<moonlighter::Recipe>,
+}
+
+impl Link {
+	/// The whole address of the web build, opening on this.
+	pub fn to_url(&self) -> String {
+		format!("{WEB_APP}#{}", self.to_fragment())
+	}
+
+	pub fn to_fragment(&self) -> String {
+		let options = &self.options;
+		let mut pairs = vec![
+			format!("affinity={:?}", options.affinity),
+			format!("vegetables={}", options.vegetables),
+			format!("max-fillers={}", options.max_fillers),
+			format!("cereals={}", if options.full_cereals { 4 } else { 1 }),
+			format!("complex-processing={}", options.complex_processing),
+			format!("custom-offset={}", options.custom_offset),
+		];
+		if let Some(player_number) = self.player_number {
+			pairs.push(format!("player-number={player_number}"));
+		}
+		if let Some(recipe) = &self.recipe {
+			let mut cereals = Vec::new();
+			let mut vegetables = Vec::new();
+			for step in core::steps(recipe) {
+				match step {
+					core::Step::Sugars(count) => pairs.push(format!("sugars={count}")),
+					core::Step::Barleys(count) => pairs.push(format!("barleys={count}")),
+					core::Step::Cereal(cereal) => cereals.push(cereal),
+					core::Step::Vegetable { vegetable, processing } => vegetables.push(format!("{vegetable}:{processing}")),
+					core::Step::Water => {}
+				}
+			}
+			pairs.push(format!("recipe-cereals={}", cereals.join(",")));
+			pairs.push(format!("vegs={}", vegetables.join(",")));
+		}
+		pairs.join("&")
+	}
+
+	/// Reads a fragment, with or without its `#`. Anything that isn't understood is left out, numbers
+	/// are kept to what the settings allow, and a fragment without an affinity isn't a link to the app.
+	pub fn from_fragment(fragment: &str) -> Option<Self> {
+		let mut options = core::Options::default();
+		let mut player_number = None;
+		let mut affinity = None;
+		let mut steps = Vec::new();
+		let mut has_recipe = false;
+		for pair in fragment.trim_start_matches('#').split('&') {
+			let Some((key, value)) = pair.split_once('=') else {
+				continue;
+			};
+			match key {
+				"affinity" => affinity = variants::parse_affinity(value),
+				"vegetables" => options.vegetables = value.parse().map_or(options.vegetables, |vegetables: u64| vegetables.clamp(1, 12)),
+				"max-fillers" => options.max_fillers = value.parse().map_or(options.max_fillers, |max_fillers: u64| max_fillers.min(worker::MAX_FILLERS)),
+				"cereals" => options.full_cereals = value != "1",
+				"complex-processing" => options.complex_processing = value == "true",
+				"custom-offset" => options.custom_offset = value.parse().unwrap_or(options.custom_offset),
+				"player-number" => player_number = value.parse::<u64>().ok().map(|number| number % 138),
+				"sugars" => steps.extend(value.parse().ok().map(core::Step::Sugars)),
+				"barleys" => steps.extend(value.parse().ok().map(core::Step::Barleys)),
+				"recipe-cereals" => steps.extend(list(value).map(|cereal| core::Step::Cereal(cereal.to_owned()))),
+				"vegs" => {
+					has_recipe = true;
+					steps.extend(list(value).filter_map(|vegetable| {
+						let (vegetable, processing) = vegetable.split_once(':')?;
+						Some(core::Step::Vegetable {
+							vegetable: vegetable.to_owned(),
+							processing: processing.to_owned(),
+						})
+					}));
+				}
+				_ => {}
+			}
+		}
+		options.affinity = affinity?;
+		Some(Self {
+			options,
+			player_number,
+			recipe: if has_recipe { core::recipe(&steps).ok() } else { None },
+		})
+	}
+}
+
+fn list(value: &str) -> impl Iterator<Item = &str> {
+	value.split(',').filter(|item| !item.is_empty())
+}
+
+/// The page's fragment, if there is one.
+#[cfg(target_arch = "wasm32")]
+pub fn read() -> Option<String> {
+	web_sys::window()?.location().hash().ok().filter(|hash| !hash.is_empty())
+}
+
+/// Puts `fragment` in the address bar if it isn't there already. The page's history is left alone,
+/// so going back still leaves the app.
+#[cfg(target_arch = "wasm32")]
+pub fn write(fragment: &str) {
+	let Some(window) = web_sys::window() else {
+		return;
+	};
+	let fragment = format!("#{fragment}");
+	if window.location().hash().ok().as_deref() == Some(fragment.as_str()) {
+		return;
+	}
+	if let Ok(history) = window.history() {
+		history.replace_state_with_url(&eframe::wasm_bindgen::JsValue::NULL, "", Some(&fragment)).ok();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fragment_reads_back() {
+		let link = Link {
+			options: core::Options {
+				affinity: moonlighter::Affinity::Carpentry,
+				vegetables: 2,
+				full_cereals: false,
+				custom_offset: 3,
+				..core::Options::default()
+			},
+			player_number: Some(57),
+			recipe: Some(core::fixtures::recipe(&[(0, 0), (1, 1)], 0, 4, 0)),
+		};
+
+		let read = Link::from_fragment(&format!("#{}", link.to_fragment())).expect("fragment not read");
+		assert!(read.options == link.options);
+		assert_eq!(read.player_number, Some(57));
+		assert_eq!(read.recipe.as_ref().map(core::steps), link.recipe.as_ref().map(core::steps));
+		assert!(Link::from_fragment("#section-2").is_none());
+	}
+
+	#[test]
+	fn fragment_numbers_are_kept_to_the_settings_range() {
+		let read = |fragment| Link::from_fragment(fragment).map(|link| (link.options.vegetables, link.options.max_fillers));
+		assert_eq!(read("affinity=Carpentry&vegetables=0&max-fillers=500"), Some((1, worker::MAX_FILLERS)));
+		assert_eq!(read("affinity=Carpentry&vegetables=99&max-fillers=0"), Some((12, 0)));
+		assert_eq!(read("affinity=Carpentry&vegetables=-1&max-fillers=many"), Some((12, 80)));
+	}
+}
@@ -24,9 +24,17 @@ fn main() {
 	// Redirect `log` message to `console.log` and friends:
 	eframe::WebLogger::init(log::LevelFilter::Debug).ok();
 
+	// The search worker loads this same module, but has no window to draw in.
+	if web_sys::window().is_none() {
+		v12::run_web_worker();
+		return;
+	}
+
 	let web_options = eframe::WebOptions::default();
+	// Read before the app starts keeping the address up to date.
+	let fragment = v12::read_link();
 
-	wasm_bindgen_futures::spawn_local(async {
+	wasm_bindgen_futures::spawn_local(async move {
 		let document = web_sys::window().expect("No window").document().expect("No document");
 
 		let canvas = document
@@ -35,7 +43,19 @@ fn main() {
 			.dyn_into::<web_sys::HtmlCanvasElement>()
 			.expect("the_canvas_id was not a HtmlCanvasElement");
 
-		let start_result = eframe::WebRunner::new().start(canvas, web_options, Box::new(|cc| Ok(Box::new(v12::TemplateApp::new(cc))))).await;
+		let start_result = eframe::WebRunner::new()
+			.start(
+				canvas,
+				web_options,
+				Box::new(move |cc| {
+					let mut app = v12::TemplateApp::new(cc);
+					if let Some(fragment) = &fragment {
+						app.open_link(fragment);
+					}
+					Ok(Box::new(app))
+				}),
+			)
+			.await;
 
 		// Remove the loading text and spinner:
 		if let Some(loading_text) = document.get_element_by_id("loading_text") {
@@ -0,0 +1,54 @@
+//! Named characters, each with its own calibration, options and inventory, for players with alts.
+
+use crate::{core, inventory};
+
+/// Where the profiles are stored, apart from the rest of the app state.
+pub const STORAGE_KEY: &str = "profiles";
+
+/// Everything that belongs to one character rather than to the app.
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Profile {
+	pub name: String,
+	pub bear_meal_affinity: moonlighter::Affinity,
+	pub observations: Vec<core::Observation>,
+	pub options: core::Options,
+	pub inventory: inventory::Inventory,
+	pub exclusions: inventory::Exclusions,
+}
+
+impl Default for Profile {
+	fn default() -> Self {
+		Self {
+			name: "Main".to_owned(),
+			bear_meal_affinity: moonlighter::Affinity::AggressiveFighting,
+			observations: Vec::new(),
+			options: core::Options::default(),
+			inventory: inventory::Inventory::default(),
+			exclusions: inventory::Exclusions::default(),
+		}
+	}
+}
+
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Profiles {
+	pub list: Vec<Profile>,
+	/// Index into `list` of the character in use. Its entry is only brought up to date on switching
+	/// and saving; in between, the app holds the live copy.
+	pub current: usize,
+}
+
+impl Profiles {
+	pub fn current_name(&self) -> &str {
+		self.list.get(self.current).map_or("", |profile| profile.name.as_str())
+	}
+
+	/// A name like `Character 2` that no profile has yet.
+	pub fn unused_name(&self) -> String {
+		(self.list.len() + 1..)
+			.map(|number| format!("Character {number}"))
+			.find(|name| self.list.iter().all(|profile| profile.name != *name))
+			.unwrap_or_default()
+	}
+}
@@ -0,0 +1,211 @@
+//! A recipe as a short code to paste to someone, like `AEDT-QAAB-...`: a few bytes in base32, with a
+//! version to read old codes by and a checksum to catch typos.
+//!
+//! Version 1 is, a byte each: the version, the affinity, the player number, the custom offset,
+//! the sugars and the barleys; then the number of cereals and each cereal; then the number of
+//! vegetables and each vegetable with its processing; then a Fletcher-16 checksum of all that.
+//! Ingredients are by their index in moonlighter's lists of variants.
+
+use crate::{core, variants};
+
+const VERSION: u8 = 1;
+
+const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Characters between groups, for reading the code out.
+const GROUP: usize = 4;
+
+/// What a share code holds.
+#[derive(Clone)]
+pub struct SharedRecipe {
+	pub affinity: moonlighter::Affinity,
+	pub player_number: u64,
+	pub custom_offset: u64,
+	pub recipe: moonlighter::Recipe,
+}
+
+impl SharedRecipe {
+	/// # Errors
+	///
+	/// If a number doesn't fit in a byte.
+	pub fn to_code(&self) -> Result<String, String> {
+		let affinity = variants::name_of(&self.affinity);
+		let mut bytes = vec![
+			VERSION,
+			index(variants::affinities(), &affinity)?,
+			byte("player number", self.player_number)?,
+			byte("custom offset", self.custom_offset)?,
+			byte("sugars", self.recipe.sugars)?,
+			byte("barleys", self.recipe.barleys)?,
+		];
+		let mut cereals = Vec::new();
+		let mut vegetables = Vec::new();
+		for step in core::steps(&self.recipe) {
+			match step {
+				core::Step::Cereal(cereal) => cereals.push(index(variants::cereals(), &cereal)?),
+				core::Step::Vegetable { vegetable, processing } => {
+					let pair = index(variants::vegetables(), &vegetable)? as usize * variants::processings().len() + index(variants::processings(), &processing)? as usize;
+					vegetables.push(byte("vegetable", pair as u64)?);
+				}
+				core::Step::Water | core::Step::Sugars(_) | core::Step::Barleys(_) => {}
+			}
+		}
+		for list in [cereals, vegetables] {
+			bytes.push(byte("ingredient count", list.len() as u64)?);
+			bytes.extend(list);
+		}
+		bytes.extend(checksum(&bytes));
+		Ok(group(&encode(&bytes)))
+	}
+
+	/// Reads a code, ignoring case, spaces and dashes.
+	///
+	/// # Errors
+	///
+	/// If the code has a typo, is from a newer version, or doesn't make a recipe.
+	pub fn from_code(code: &str) -> Result<Self, String> {
+		let bytes = decode(code).ok_or("Not a share code")?;
+		let Some((data, sum)) = bytes.split_last_chunk::<2>() else {
+			return Err("This code is too short".to_owned());
+		};
+		if checksum(data) != *sum {
+			return Err("This code has a typo".to_owned());
+		}
+		let mut bytes = data.iter().copied();
+		let mut next = || bytes.next().ok_or("This code is cut short");
+		let version = next()?;
+		if version != VERSION {
+			return Err(format!("This code is from another version of V12 (code version {version})"));
+		}
+		let affinity = variants::affinities()
+			.get(usize::from(next()?))
+			.and_then(|name| variants::parse_affinity(name))
+			.ok_or("Unknown affinity")?;
+		let player_number = u64::from(next()?) % 138;
+		let custom_offset = u64::from(next()?);
+		let mut steps = vec![core::Step::Sugars(u64::from(next()?)), core::Step::Barleys(u64::from(next()?))];
+		for _ in 0..next()? {
+			steps.push(core::Step::Cereal(name(variants::cereals(), usize::from(next()?))?));
+		}
+		for _ in 0..next()? {
+			let pair = usize::from(next()?);
+			let processings = variants::processings().len().max(1);
+			steps.push(core::Step::Vegetable {
+				vegetable: name(variants::vegetables(), pair / processings)?,
+				processing: name(variants::processings(), pair % processings)?,
+			});
+		}
+		if next().is_ok() {
+			return Err("This code has more in it than a recipe".to_owned());
+		}
+		Ok(Self {
+			affinity,
+			player_number,
+			custom_offset,
+			recipe: core::recipe(&steps)?,
+		})
+	}
+}
+
+fn byte(what: &str, value: u64) -> Result<u8, String> {
+	u8::try_from(value).map_err(|error| format!("Too many {what} for a share code: {value}: {error}"))
+}
+
+fn index(names: &[&str], name: &str) -> Result<u8, String> {
+	let index = names.iter().position(|variant| *variant == name).ok_or_else(|| format!("Unknown {name}"))?;
+	byte("variants", index as u64)
+}
+
+fn name(names: &[&str], index: usize) -> Result<String, String> {
+	names.get(index).map(|name| (*name).to_owned()).ok_or_else(|| "Unknown ingredient".to_owned())
+}
+
+fn checksum(bytes: &[u8]) -> [u8; 2] {
+	let (mut low, mut high) = (0_u16, 0_u16);
+	for byte in bytes {
+		low = (low + u16::from(*byte)) % 255;
+		high = (high + low) % 255;
+	}
+	[high as u8, low as u8]
+}
+
+/// Base32 without padding.
+fn encode(bytes: &[u8]) -> String {
+	let mut code = String::new();
+	let (mut buffer, mut bits) = (0_u32, 0);
+	for byte in bytes {
+		buffer = (buffer << 8) | u32::from(*byte);
+		bits += 8;
+		while bits >= 5 {
+			bits -= 5;
+			code.push(letter(buffer >> bits));
+		}
+	}
+	if bits > 0 {
+		code.push(letter(buffer << (5 - bits)));
+	}
+	code
+}
+
+/// The letter for the lowest 5 bits.
+fn letter(bits: u32) -> char {
+	ALPHABET.get((bits & 31) as usize).map_or('A', |letter| char::from(*letter))
+}
+
+fn decode(code: &str) -> Option<Vec<u8>> {
+	let mut bytes = Vec::new();
+	let (mut buffer, mut bits) = (0_u32, 0);
+	for c in code.chars().filter(|c| !c.is_whitespace() && *c != '-') {
+		let value = ALPHABET.iter().position(|letter| char::from(*letter) == c.to_ascii_uppercase())?;
+		buffer = (buffer << 5) | value as u32;
+		bits += 5;
+		if bits >= 8 {
+			bits -= 8;
+			bytes.push((buffer >> bits) as u8);
+		}
+	}
+	Some(bytes)
+}
+
+fn group(code: &str) -> String {
+	code.as_bytes().chunks(GROUP).map(|chunk| String::from_utf8_lossy(chunk)).collect::<Vec<_>>().join("-")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn shared() -> SharedRecipe {
+		let processings = variants::processings().len();
+		let vegetables: Vec<(usize, usize)> = (0..variants::vegetables().len()).map(|index| (index, index % processings)).collect();
+		SharedRecipe {
+			affinity: moonlighter::Affinity::Carpentry,
+			player_number: 137,
+			custom_offset: 2,
+			recipe: core::fixtures::recipe(&vegetables, variants::cereals().len(), 17, 3),
+		}
+	}
+
+	#[test]
+	fn code_reads_back() {
+		let shared = shared();
+		let code = shared.to_code().expect("no code");
+		let read = SharedRecipe::from_code(&code.to_lowercase()).expect("code not read");
+		assert_eq!(read.affinity, shared.affinity);
+		assert_eq!(read.player_number, shared.player_number);
+		assert_eq!(read.custom_offset, shared.custom_offset);
+		assert_eq!(core::steps(&read.recipe), core::steps(&shared.recipe));
+	}
+
+	#[test]
+	fn code_catches_typos() {
+		let code = shared().to_code().expect("no code");
+		let mut typo: Vec<char> = code.chars().collect();
+		if let Some(c) = typo.get_mut(6) {
+			*c = if *c == 'A' { 'B' } else { 'A' };
+		}
+		let typo: String = typo.into_iter().collect();
+		assert_eq!(SharedRecipe::from_code(&typo).err().as_deref(), Some("This code has a typo"));
+		assert!(SharedRecipe::from_code("not a code!").is_err());
+	}
+}
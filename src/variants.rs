@@ -0,0 +1,175 @@
+//! Names of the variants of moonlighter's enums.
+//!
+//! moonlighter has no way to list them, but its serde derives know them: deserializing an enum hands
+//! the list of variant names to the deserializer, so a deserializer that stops right there can read
+//! them off. The names are the ones serde uses, which for these enums are the Rust names.
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, Visitor};
+
+/// Carries the names found out of the deserializer, disguised as an error.
+#[derive(Debug)]
+enum Probe {
+	Found(&'static [&'static str]),
+	Failed(String),
+}
+
+impl std::fmt::Display for Probe {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Found(variants) => write!(f, "found variants {variants:?}"),
+			Self::Failed(msg) => f.write_str(msg),
+		}
+	}
+}
+
+impl std::error::Error for Probe {}
+
+impl de::Error for Probe {
+	fn custom<T: std::fmt::Display>(msg: T) -> Self {
+		Self::Failed(msg.to_string())
+	}
+}
+
+/// The way from the type being deserialized down to the enum.
+#[derive(Clone, Copy)]
+enum Step {
+	/// Into a struct field.
+	Field(&'static str),
+	/// Into the first element of a sequence or tuple.
+	Element,
+}
+
+struct Prober<'a> {
+	path: &'a [Step],
+}
+
+impl<'de> de::Deserializer<'de> for Prober<'_> {
+	type Error = Probe;
+
+	fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Probe> {
+		Err(Probe::Failed("not the way to an enum".to_owned()))
+	}
+
+	fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, Probe> {
+		if self.path.is_empty() { Err(Probe::Found(variants)) } else { self.deserialize_any(visitor) }
+	}
+
+	fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, Probe> {
+		match self.path {
+			[Step::Field(field), rest @ ..] => visitor.visit_map(FieldAccess { field: Some(field), rest }),
+			_ => self.deserialize_any(visitor),
+		}
+	}
+
+	fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Probe> {
+		match self.path {
+			[Step::Element, rest @ ..] => visitor.visit_seq(ElementAccess { done: false, rest }),
+			_ => self.deserialize_any(visitor),
+		}
+	}
+
+	fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Probe> {
+		self.deserialize_seq(visitor)
+	}
+
+	fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Probe> {
+		visitor.visit_some(self)
+	}
+
+	fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Probe> {
+		visitor.visit_newtype_struct(self)
+	}
+
+	serde::forward_to_deserialize_any! {
+		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf unit unit_struct map tuple_struct identifier ignored_any
+	}
+}
+
+/// A struct with just the one field on the way.
+struct FieldAccess<'a> {
+	field: Option<&'static str>,
+	rest: &'a [Step],
+}
+
+impl<'de> de::MapAccess<'de> for FieldAccess<'_> {
+	type Error = Probe;
+
+	fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Probe> {
+		self.field.take().map(|field| seed.deserialize(de::value::StrDeserializer::new(field))).transpose()
+	}
+
+	fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value, Probe> {
+		seed.deserialize(Prober { path: self.rest })
+	}
+}
+
+/// A sequence with just the one element on the way.
+struct ElementAccess<'a> {
+	done: bool,
+	rest: &'a [Step],
+}
+
+impl<'de> de::SeqAccess<'de> for ElementAccess<'_> {
+	type Error = Probe;
+
+	fn next_element_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<Option<S::Value>, Probe> {
+		if std::mem::replace(&mut self.done, true) {
+			return Ok(None);
+		}
+		seed.deserialize(Prober { path: self.rest }).map(Some)
+	}
+}
+
+fn probe<T: DeserializeOwned>(path: &[Step]) -> &'static [&'static str] {
+	match T::deserialize(Prober { path }) {
+		Err(Probe::Found(variants)) => variants,
+		Err(Probe::Failed(msg)) => {
+			log::error!("Couldn't list variants: {msg}");
+			&[]
+		}
+		Ok(_) => &[],
+	}
+}
+
+pub fn affinities() -> &'static [&'static str] {
+	probe::<moonlighter::Affinity>(&[])
+}
+
+/// Reads an affinity from its name, matching loosely.
+pub fn parse_affinity(name: &str) -> Option<moonlighter::Affinity> {
+	let name = normalize(name);
+	let variant = affinities().iter().find(|variant| normalize(variant) == name)?;
+	serde::Deserialize::deserialize(de::value::StrDeserializer::<de::value::Error>::new(variant)).ok()
+}
+
+pub fn vegetables() -> &'static [&'static str] {
+	probe::<moonlighter::Recipe>(&[Step::Field("vegs"), Step::Element])
+}
+
+pub fn processings() -> &'static [&'static str] {
+	probe::<moonlighter::Recipe>(&[Step::Field("processings"), Step::Element, Step::Element])
+}
+
+pub fn cereals() -> &'static [&'static str] {
+	probe::<moonlighter::Recipe>(&[Step::Field("cereals"), Step::Element])
+}
+
+/// The variant name of a value of one of moonlighter's enums.
+pub fn name_of<T: serde::Serialize>(value: &T) -> String {
+	match serde_json::to_value(value) {
+		Ok(serde_json::Value::String(name)) => name,
+		_ => String::new(),
+	}
+}
+
+/// The variant among `names` called `name`, matching loosely.
+pub fn find(names: &[&'static str], name: &str) -> Option<&'static str> {
+	let name = normalize(name);
+	names.iter().copied().find(|variant| normalize(variant) == name)
+}
+
+/// Lowercase without spaces or punctuation, so `Sweet potato:` matches `SweetPotato`.
+pub fn normalize(name: &str) -> String {
+	name.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
@@ -0,0 +1,881 @@
+//! Runs recipe searches off the UI thread: a native thread on desktop, a Web Worker on the web.
+
+use std::sync::{
+	Arc,
+	atomic::{AtomicBool, Ordering},
+	mpsc,
+};
+
+use crate::core;
+use crate::inventory::{Exclusions, Inventory};
+
+/// Everything the solver needs, in a form that can be sent to a worker.
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub struct SearchRequest {
+	pub options: core::Options,
+	pub player_number: u64,
+	/// How many recipes to list, the best one included. The solver gives one recipe per set of
+	/// settings, so the others come from nearby settings: fewer vegetables, no complex processing or
+	/// the other cereal choice.
+	pub alternatives: u64,
+	/// Recipes the inventory can't make are passed over. Nearby settings are searched as well when
+	/// it's on, in case the requested ones only give recipes that need something missing.
+	pub inventory: Inventory,
+	/// Recipes using any of these are passed over, and nearby settings searched, as for the inventory.
+	pub exclusions: Exclusions,
+	/// Whether to make the runs with fewer vegetables first, for candidates to show while the full
+	/// run is going. Only worth it with someone watching.
+	#[serde(default)]
+	pub candidates: bool,
+}
+
+/// The settings that vary between the solver runs of one search.
+#[derive(Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Run {
+	pub vegetables: u64,
+	pub complex_processing: bool,
+	pub full_cereals: bool,
+}
+
+impl Run {
+	pub fn of(options: &core::Options) -> Self {
+		Self {
+			vegetables: options.vegetables,
+			complex_processing: options.complex_processing,
+			full_cereals: options.full_cereals,
+		}
+	}
+
+	/// Changes `options` to these settings.
+	pub fn apply(&self, options: &mut core::Options) {
+		options.vegetables = self.vegetables;
+		options.complex_processing = self.complex_processing;
+		options.full_cereals = self.full_cereals;
+	}
+}
+
+impl SearchRequest {
+	fn solver_options(&self, run: &Run) -> moonlighter::Options {
+		moonlighter::Options {
+			vegs: run.vegetables,
+			complex_processing: run.complex_processing,
+			full_cereals: run.full_cereals,
+			..self.options.solver_options(self.player_number)
+		}
+	}
+
+	fn requested_run(&self) -> Run {
+		Run::of(&self.options)
+	}
+
+	/// The solver runs of the search, in order: every vegetable count up to the requested one if
+	/// asked for `candidates`, then the nearby settings that alternatives come from.
+	///
+	/// Runs with fewer vegetables are much cheaper than the full one, so they cost little and give
+	/// the user something to use while the full run is going.
+	pub fn runs(&self) -> Vec<Run> {
+		let requested = self.requested_run();
+		let fewest = if self.candidates { 1 } else { self.options.vegetables };
+		let mut runs: Vec<Run> = (fewest..=self.options.vegetables).map(|vegetables| Run { vegetables, ..requested.clone() }).collect();
+		if self.alternatives > 1 || self.inventory.enabled || !self.exclusions.is_empty() {
+			for vegetables in (self.options.vegetables.saturating_sub(2).max(1)..=self.options.vegetables).rev() {
+				for complex_processing in [self.options.complex_processing, false] {
+					for full_cereals in [self.options.full_cereals, !self.options.full_cereals] {
+						let run = Run {
+							vegetables,
+							complex_processing,
+							full_cereals,
+						};
+						if !runs.contains(&run) {
+							runs.push(run);
+						}
+					}
+				}
+			}
+		}
+		runs
+	}
+}
+
+/// A recipe together with the settings it was found with.
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub struct Alternative {
+	pub run: Run,
+	pub recipe: moonlighter::Recipe,
+}
+
+/// What a running search reports back to the UI.
+#[derive(serde::Deserialize, serde::Serialize)]
+pub enum Message {
+	Progress(Progress),
+	/// A usable recipe with fewer vegetables than asked for, found on the way to the full run.
+	/// Each one has more vegetables than the last.
+	Candidate(moonlighter::Recipe),
+	/// The best recipe for the requested settings, if there is one.
+	Best(Option<moonlighter::Recipe>),
+	/// Why the requested settings gave no recipe. Sent just before `Finished` when they didn't.
+	Diagnosis(Diagnosis),
+	/// The search is over. Carries the distinct recipes found, best first, as many as were asked for.
+	Finished(Vec<Alternative>),
+}
+
+/// The most sugars and barleys a search may be asked to add.
+pub const MAX_FILLERS: u64 = 80;
+
+/// Why a search found no recipe for the requested settings, as the smallest changes that would
+/// give one.
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub struct Diagnosis {
+	/// The solver did find a recipe, but the inventory or exclusions rule it out.
+	pub ruled_out: bool,
+	/// The fewest maximum sugars and barleys that give a recipe.
+	pub max_fillers: Option<u64>,
+	/// The most vegetables, below the requested number, that give a recipe.
+	pub vegetables: Option<u64>,
+	/// Whether the other cereal setting gives a recipe.
+	pub other_cereals: bool,
+	/// The least extra custom offset, up to 3, that gives a recipe.
+	pub extra_offset: Option<u64>,
+}
+
+/// Tells a running search to stop. Clones share the same flag.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+	pub fn cancel(&self) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+
+	pub fn is_cancelled(&self) -> bool {
+		self.0.load(Ordering::Relaxed)
+	}
+}
+
+/// How far a search has got.
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub struct Progress {
+	/// Index into [`SearchRequest::runs`] of the run going now.
+	pub run: usize,
+	pub runs: usize,
+	pub vegetables: u64,
+	/// Ingredient count of the best recipe found so far.
+	pub best_length: Option<u64>,
+	/// The planned runs are done and found nothing; now trying changed settings to see why.
+	pub diagnosing: bool,
+}
+
+/// Ingredient count of a recipe: water, sugars, barleys, cereals and vegetables.
+pub fn recipe_length(recipe: &moonlighter::Recipe) -> u64 {
+	1 + recipe.sugars + recipe.barleys + recipe.cereals.len() as u64 + recipe.vegs.len() as u64
+}
+
+/// What the solver makes of one set of options, with the inventory and exclusions applied.
+enum Outcome {
+	Found(moonlighter::Recipe),
+	/// The solver found a recipe, but the inventory or exclusions rule it out. The solver knows
+	/// nothing of them, so they can only weed out what it finds.
+	RuledOut(moonlighter::Recipe),
+	Nothing,
+}
+
+fn solve(request: &SearchRequest, options: &moonlighter::Options) -> Outcome {
+	match moonlighter::find_recipe(options) {
+		Some(recipe) if request.inventory.allows(&recipe) && request.exclusions.allows(&recipe) => Outcome::Found(recipe),
+		Some(recipe) => Outcome::RuledOut(recipe),
+		None => Outcome::Nothing,
+	}
+}
+
+/// Runs the search, reporting progress, candidates and results through `report`.
+///
+/// A single solver run can't be interrupted, so `cancel` is checked between runs.
+pub fn search(request: &SearchRequest, cancel: &CancelToken, report: &mut impl FnMut(Message)) {
+	let runs = request.runs();
+	let requested = request.requested_run();
+	let mut best_length = None;
+	let mut found = Vec::new();
+	// Whether the requested run has been made, and whether it turned up a usable recipe.
+	let mut requested_done = false;
+	let mut requested_found = false;
+	let mut ruled_out = false;
+	for (index, run) in runs.iter().enumerate() {
+		if cancel.is_cancelled() {
+			return;
+		}
+		report(Message::Progress(Progress {
+			run: index,
+			runs: runs.len(),
+			vegetables: run.vegetables,
+			best_length,
+			diagnosing: false,
+		}));
+		let outcome = solve(request, &request.solver_options(run));
+		if !requested_done {
+			if let Outcome::Found(recipe) = &outcome {
+				best_length = Some(recipe_length(recipe));
+			}
+			if *run == requested {
+				requested_done = true;
+				requested_found = matches!(outcome, Outcome::Found(_));
+				ruled_out = matches!(outcome, Outcome::RuledOut(_));
+				report(Message::Best(match &outcome {
+					Outcome::Found(recipe) => Some(recipe.clone()),
+					Outcome::RuledOut(_) | Outcome::Nothing => None,
+				}));
+			} else if let Outcome::Found(recipe) = &outcome {
+				report(Message::Candidate(recipe.clone()));
+			}
+		}
+		if let Outcome::Found(recipe) = outcome {
+			found.push(Alternative { run: run.clone(), recipe });
+		}
+	}
+
+	if !requested_found {
+		let mut progress = |vegetables| {
+			report(Message::Progress(Progress {
+				run: runs.len(),
+				runs: runs.len(),
+				vegetables,
+				best_length,
+				diagnosing: true,
+			}));
+		};
+		let Some(diagnosis) = diagnose(request, &found, ruled_out, cancel, &mut progress) else {
+			return;
+		};
+		report(Message::Diagnosis(diagnosis));
+	}
+	if !cancel.is_cancelled() {
+		report(Message::Finished(rank_alternatives(found, &requested, request.alternatives)));
+	}
+}
+
+/// Works out why the requested settings give no recipe, by trying the smallest changes to them that
+/// might. Returns `None` if cancelled.
+fn diagnose(request: &SearchRequest, found: &[Alternative], ruled_out: bool, cancel: &CancelToken, progress: &mut impl FnMut(u64)) -> Option<Diagnosis> {
+	let requested = request.requested_run();
+	let mut try_options = |options: moonlighter::Options| {
+		if cancel.is_cancelled() {
+			return None;
+		}
+		progress(options.vegs);
+		Some(solve(request, &options))
+	};
+
+	let filtered = request.inventory.enabled || !request.exclusions.is_empty();
+	let max_fillers = fewest_fillers(request.options.max_fillers, filtered, |max_fillers| {
+		try_options(moonlighter::Options {
+			max_fillers,
+			..request.solver_options(&requested)
+		})
+		.unwrap_or(Outcome::Nothing)
+	});
+	if cancel.is_cancelled() {
+		return None;
+	}
+	let mut works = |options| try_options(options).map(|outcome| matches!(outcome, Outcome::Found(_)));
+
+	// Runs with fewer vegetables may have been made already, for candidates or alternatives.
+	let made = request.runs();
+	let mut vegetables = found
+		.iter()
+		.filter(|alternative| {
+			alternative.run.vegetables < requested.vegetables && alternative.run.complex_processing == requested.complex_processing && alternative.run.full_cereals == requested.full_cereals
+		})
+		.map(|alternative| alternative.run.vegetables)
+		.max();
+	if vegetables.is_none() {
+		for fewer in (1..requested.vegetables).rev() {
+			let run = Run {
+				vegetables: fewer,
+				..requested.clone()
+			};
+			if !made.contains(&run) && works(request.solver_options(&run))? {
+				vegetables = Some(fewer);
+				break;
+			}
+		}
+	}
+
+	let other_cereals = Run {
+		full_cereals: !requested.full_cereals,
+		..requested.clone()
+	};
+	let other_cereals = found.iter().any(|alternative| alternative.run == other_cereals) || works(request.solver_options(&other_cereals))?;
+
+	let mut extra_offset = None;
+	for extra in 1..=3 {
+		if works(moonlighter::Options {
+			custom_offset: request.options.custom_offset + extra,
+			..request.solver_options(&requested)
+		})? {
+			extra_offset = Some(extra);
+			break;
+		}
+	}
+
+	Some(Diagnosis {
+		ruled_out,
+		max_fillers,
+		vegetables,
+		other_cereals,
+		extra_offset,
+	})
+}
+
+/// How many solver runs [`fewest_fillers`] may make when recipes are filtered.
+const FILTERED_FILLER_TRIES: usize = 8;
+
+/// The fewest maximum sugars and barleys above `failing`, up to [`MAX_FILLERS`], that give a usable
+/// recipe when `solve`d with.
+///
+/// Allowing more fillers never loses the solver a recipe, so the answer can be bisected. When the
+/// recipes are `filtered` by the inventory or exclusions, though, more fillers can lead to a recipe
+/// that gets ruled out. Then the counts are tried from the most down: every maximum from the fillers
+/// the solver's recipe uses up to the one tried gives that same recipe, so the next try is just
+/// below them. The scan ends when the solver finds nothing, or after [`FILTERED_FILLER_TRIES`] tries
+/// with the fewest found so far.
+fn fewest_fillers(failing: u64, filtered: bool, mut solve: impl FnMut(u64) -> Outcome) -> Option<u64> {
+	if failing >= MAX_FILLERS {
+		return None;
+	}
+	if filtered {
+		let mut fewest = None;
+		let mut max_fillers = MAX_FILLERS;
+		for _ in 0..FILTERED_FILLER_TRIES {
+			let used = match solve(max_fillers) {
+				Outcome::Found(recipe) => {
+					let used = (recipe.sugars + recipe.barleys).clamp(failing + 1, max_fillers);
+					fewest = Some(used);
+					used
+				}
+				Outcome::RuledOut(recipe) => (recipe.sugars + recipe.barleys).min(max_fillers),
+				Outcome::Nothing => break,
+			};
+			if used <= failing + 1 {
+				break;
+			}
+			max_fillers = used - 1;
+		}
+		return fewest;
+	}
+	let mut works = |max_fillers| matches!(solve(max_fillers), Outcome::Found(_));
+	if !works(MAX_FILLERS) {
+		return None;
+	}
+	let (mut failing, mut working) = (failing, MAX_FILLERS);
+	while working - failing > 1 {
+		let middle = failing + (working - failing) / 2;
+		if works(middle) {
+			working = middle;
+		} else {
+			failing = middle;
+		}
+	}
+	Some(working)
+}
+
+/// Orders recipes by vegetable count, then length, then whether they use the requested settings, and
+/// keeps the first `count` distinct ones.
+fn rank_alternatives(mut found: Vec<Alternative>, requested: &Run, count: u64) -> Vec<Alternative> {
+	found.sort_by_key(|alternative| (std::cmp::Reverse(alternative.recipe.vegs.len()), recipe_length(&alternative.recipe), alternative.run != *requested));
+	let mut seen = Vec::new();
+	found.retain(|alternative| {
+		// Different settings often land on the same recipe.
+		let key = serde_json::to_string(&alternative.recipe).unwrap_or_default();
+		if seen.contains(&key) {
+			false
+		} else {
+			seen.push(key);
+			true
+		}
+	});
+	found.truncate(usize::try_from(count).unwrap_or(usize::MAX));
+	found
+}
+
+/// Turns the progress reports of a running search into elapsed time and an estimate of the rest,
+/// on the UI side where the clock is.
+pub struct ProgressTracker {
+	started: f64,
+	runs: Vec<Run>,
+	run_started: f64,
+	/// How long each finished run took, in order.
+	durations: Vec<f64>,
+	progress: Option<Progress>,
+}
+
+impl ProgressTracker {
+	pub fn new(now: f64, runs: Vec<Run>) -> Self {
+		Self {
+			started: now,
+			runs,
+			run_started: now,
+			durations: Vec::new(),
+			progress: None,
+		}
+	}
+
+	pub fn record(&mut self, progress: Progress, now: f64) {
+		if self.progress.is_some() {
+			self.durations.push(now - self.run_started);
+		}
+		self.run_started = now;
+		self.progress = Some(progress);
+	}
+
+	pub fn progress(&self) -> Option<&Progress> {
+		self.progress.as_ref()
+	}
+
+	pub fn elapsed(&self, now: f64) -> f64 {
+		now - self.started
+	}
+
+	/// Seconds left. Each remaining run is assumed to take as long as a finished run with as many
+	/// vegetables, or failing that, to keep up the growth in run time seen between the last two
+	/// vegetable counts.
+	pub fn eta(&self, now: f64) -> Option<f64> {
+		let progress = self.progress.as_ref()?;
+		let finished: Vec<(u64, f64)> = self.runs.iter().zip(&self.durations).map(|(run, duration)| (run.vegetables, *duration)).collect();
+		let growth = finished
+			.windows(2)
+			.filter_map(|pair| match *pair {
+				[(fewer, before), (more, after)] if more == fewer + 1 && before > 0.0 => Some((after / before).clamp(1.0, 100.0)),
+				_ => None,
+			})
+			.next_back();
+		let &(last_vegetables, last_duration) = finished.iter().max_by_key(|(vegetables, _)| *vegetables)?;
+
+		let mut remaining = 0.0;
+		for run in self.runs.iter().skip(progress.run) {
+			let same = finished.iter().filter(|(vegetables, _)| *vegetables == run.vegetables).map(|(_, duration)| *duration).reduce(f64::max);
+			remaining += match same {
+				Some(duration) => duration,
+				None => last_duration * growth?.powi(i32::try_from(run.vegetables.saturating_sub(last_vegetables)).unwrap_or(i32::MAX)),
+			};
+		}
+		Some((remaining - (now - self.run_started)).max(0.0))
+	}
+
+	/// Fraction done, by time if there is an estimate and by runs otherwise.
+	pub fn fraction(&self, now: f64) -> f32 {
+		if let Some(eta) = self.eta(now) {
+			let elapsed = self.elapsed(now);
+			return (elapsed / (elapsed + eta).max(f64::EPSILON)) as f32;
+		}
+		self.progress.as_ref().map_or(0.0, |progress| progress.run as f32 / progress.runs.max(1) as f32)
+	}
+}
+
+/// A search in flight. Dropping it abandons the search.
+pub struct Job {
+	receiver: mpsc::Receiver<Message>,
+	cancel: CancelToken,
+	#[cfg(not(target_arch = "wasm32"))]
+	thread: std::thread::JoinHandle<()>,
+	#[cfg(target_arch = "wasm32")]
+	worker: Option<web::Worker>,
+}
+
+impl Job {
+	/// Starts a search. If `after` is a cancelled search that hasn't stopped yet, this one waits for
+	/// it, so there is only ever one solver run going per caller.
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn spawn(ctx: &egui::Context, request: &SearchRequest, after: Option<Self>) -> Self {
+		let (sender, receiver) = mpsc::channel();
+		let ctx = ctx.clone();
+		let request = request.clone();
+		let cancel = CancelToken::default();
+		let thread_cancel = cancel.clone();
+		let thread = std::thread::spawn(move || {
+			if let Some(after) = after
+				&& after.thread.join().is_err()
+			{
+				log::error!("A cancelled search panicked");
+			}
+			search(&request, &thread_cancel, &mut |message| {
+				if sender.send(message).is_ok() {
+					ctx.request_repaint();
+				}
+			});
+			// Lets the UI see that a cancelled search has stopped.
+			ctx.request_repaint();
+		});
+		Self { receiver, cancel, thread }
+	}
+
+	/// Starts a search. A cancelled web search stops at once, so `after` never has to be waited for.
+	#[cfg(target_arch = "wasm32")]
+	pub fn spawn(ctx: &egui::Context, request: &SearchRequest, after: Option<Self>) -> Self {
+		drop(after);
+		let (sender, receiver) = mpsc::channel();
+		let worker = match web::Worker::spawn(ctx, request, sender.clone()) {
+			Ok(worker) => Some(worker),
+			Err(err) => {
+				// Better a frozen tab than no recipe at all.
+				log::error!("Failed to start search worker, searching on the main thread: {err:?}");
+				search(request, &CancelToken::default(), &mut |message| {
+					sender.send(message).ok();
+				});
+				None
+			}
+		};
+		Self {
+			receiver,
+			cancel: CancelToken::default(),
+			worker,
+		}
+	}
+
+	/// Returns the next message from the search, if one has arrived.
+	pub fn poll(&self) -> Option<Message> {
+		self.receiver.try_recv().ok()
+	}
+
+	/// Stops the search. On the web this terminates the worker outright; natively the thread notices
+	/// the cancelled token when the current solver run ends and exits without reporting. Keep the job
+	/// until [`Self::has_stopped`], and pass it to the next [`Self::spawn`].
+	pub fn cancel(&self) {
+		self.cancel.cancel();
+		#[cfg(target_arch = "wasm32")]
+		if let Some(worker) = &self.worker {
+			worker.terminate();
+		}
+	}
+
+	/// Whether the thread has exited, after finishing or being cancelled.
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn has_stopped(&self) -> bool {
+		self.thread.is_finished()
+	}
+
+	/// Whether the search is no longer running: cancelled, or run on the main thread.
+	#[cfg(target_arch = "wasm32")]
+	pub fn has_stopped(&self) -> bool {
+		self.worker.is_none() || self.cancel.is_cancelled()
+	}
+}
+
+/// Entry point of the search worker on the web: the worker loads the same wasm module as the page,
+/// and `main` calls this instead of starting eframe.
+#[cfg(target_arch = "wasm32")]
+pub fn run_web_worker() {
+	web::serve();
+}
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+	use eframe::wasm_bindgen::{JsCast as _, JsValue, closure::Closure};
+
+	use super::{Message, SearchRequest, search};
+
+	/// Messages on the wire between the page and the worker.
+	#[derive(serde::Deserialize, serde::Serialize)]
+	enum Reply {
+		/// The worker has loaded and can take a request.
+		Ready,
+		Message(Message),
+	}
+
+	pub struct Worker {
+		worker: web_sys::Worker,
+		_onmessage: Closure<dyn FnMut(web_sys::MessageEvent)>,
+	}
+
+	impl Worker {
+		pub fn spawn(ctx: &egui::Context, request: &SearchRequest, sender: std::sync::mpsc::Sender<super::Message>) -> Result<Self, JsValue> {
+			let document = web_sys::window().and_then(|window| window.document()).ok_or("No document")?;
+			let base = document.base_uri()?.ok_or("No base URI")?;
+			let script_url = web_sys::Url::new_with_base("v12.js", &base)?.href();
+			let wasm_url = web_sys::Url::new_with_base("v12_bg.wasm", &base)?.href();
+			let script = format!("import init from {script_url:?}; init({{ module_or_path: {wasm_url:?} }});");
+
+			let blob_options = web_sys::BlobPropertyBag::new();
+			blob_options.set_type("text/javascript");
+			let blob = web_sys::Blob::new_with_str_sequence_and_options(&js_sys::Array::of1(&script.into()), &blob_options)?;
+			let blob_url = web_sys::Url::create_object_url_with_blob(&blob)?;
+
+			let worker_options = web_sys::WorkerOptions::new();
+			worker_options.set_type(web_sys::WorkerType::Module);
+			let worker = web_sys::Worker::new_with_options(&blob_url, &worker_options)?;
+			web_sys::Url::revoke_object_url(&blob_url)?;
+
+			let request = serde_json::to_string(request).map_err(|err| err.to_string())?;
+			let reply_worker = worker.clone();
+			let ctx = ctx.clone();
+			let onmessage = Closure::<dyn FnMut(web_sys::MessageEvent)>::new(move |event: web_sys::MessageEvent| match event.data().as_string().map(|json| serde_json::from_str(&json)) {
+				Some(Ok(Reply::Ready)) => {
+					if let Err(err) = reply_worker.post_message(&request.as_str().into()) {
+						log::error!("Failed to send request to search worker: {err:?}");
+					}
+				}
+				Some(Ok(Reply::Message(message))) => {
+					if sender.send(message).is_ok() {
+						ctx.request_repaint();
+					}
+				}
+				_ => log::error!("Unexpected message from search worker"),
+			});
+			worker.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+
+			Ok(Self { worker, _onmessage: onmessage })
+		}
+
+		pub fn terminate(&self) {
+			self.worker.terminate();
+		}
+	}
+
+	impl Drop for Worker {
+		fn drop(&mut self) {
+			self.terminate();
+		}
+	}
+
+	fn post(scope: &web_sys::DedicatedWorkerGlobalScope, reply: &Reply) {
+		match serde_json::to_string(reply) {
+			Ok(json) => {
+				if let Err(err) = scope.post_message(&json.into()) {
+					log::error!("Failed to post from search worker: {err:?}");
+				}
+			}
+			Err(err) => log::error!("Failed to encode search worker reply: {err}"),
+		}
+	}
+
+	pub fn serve() {
+		let scope: web_sys::DedicatedWorkerGlobalScope = js_sys::global().unchecked_into();
+		let reply_scope = scope.clone();
+		let onmessage = Closure::<dyn FnMut(web_sys::MessageEvent)>::new(
+			move |event: web_sys::MessageEvent| match event.data().as_string().map(|json| serde_json::from_str::<SearchRequest>(&json)) {
+				Some(Ok(request)) => {
+					// Cancelling terminates the whole worker, so this token is never set.
+					search(&request, &super::CancelToken::default(), &mut |message| post(&reply_scope, &Reply::Message(message)));
+				}
+				_ => log::error!("Search worker got a malformed request"),
+			},
+		);
+		scope.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+		onmessage.forget();
+		post(&scope, &Reply::Ready);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::core::fixtures;
+
+	fn request(vegetables: u64, alternatives: u64) -> SearchRequest {
+		SearchRequest {
+			options: core::Options {
+				vegetables,
+				..core::Options::default()
+			},
+			player_number: 57,
+			alternatives,
+			inventory: Inventory::default(),
+			exclusions: Exclusions::default(),
+			candidates: true,
+		}
+	}
+
+	fn alternative(vegetables: u64, full_cereals: bool, recipe: moonlighter::Recipe) -> Alternative {
+		Alternative {
+			run: Run {
+				vegetables,
+				complex_processing: false,
+				full_cereals,
+			},
+			recipe,
+		}
+	}
+
+	#[test]
+	fn request_reaches_the_solver_intact() {
+		let mut request = request(7, 1);
+		request.options.max_fillers = 20;
+		request.options.custom_offset = 5;
+		let json = serde_json::to_string(&request).expect("request doesn't encode");
+		let request = serde_json::from_str::<SearchRequest>(&json).expect("request doesn't decode");
+		let options = request.solver_options(&request.requested_run());
+		assert_eq!(options.affinity, moonlighter::Affinity::AggressiveFighting);
+		assert_eq!(options.vegs, 7);
+		assert_eq!(options.max_fillers, 20);
+		assert!(!options.complex_processing);
+		assert!(options.full_cereals);
+		assert_eq!(options.player_number, 57);
+		assert_eq!(options.custom_offset, 5);
+	}
+
+	#[test]
+	fn cancelled_search_reports_nothing() {
+		let cancel = CancelToken::default();
+		cancel.clone().cancel();
+		assert!(cancel.is_cancelled());
+		let mut messages = 0;
+		search(&request(3, 3), &cancel, &mut |_| messages += 1);
+		assert_eq!(messages, 0);
+		assert!(diagnose(&request(3, 1), &[], false, &cancel, &mut |_| ()).is_none());
+	}
+
+	#[cfg(not(target_arch = "wasm32"))]
+	#[test]
+	fn cancelled_job_stops_and_the_next_one_runs() {
+		let wait = |job: &Job| {
+			let started = std::time::Instant::now();
+			while !job.has_stopped() {
+				assert!(started.elapsed().as_secs() < 60, "search didn't stop");
+				std::thread::sleep(std::time::Duration::from_millis(5));
+			}
+		};
+		let ctx = egui::Context::default();
+		let cancelled = Job::spawn(&ctx, &request(1, 1), None);
+		cancelled.cancel();
+		wait(&cancelled);
+
+		let next = Job::spawn(&ctx, &request(1, 1), Some(cancelled));
+		wait(&next);
+		let mut last = None;
+		while let Some(message) = next.poll() {
+			last = Some(message);
+		}
+		assert!(matches!(last, Some(Message::Finished(_))));
+	}
+
+	#[test]
+	fn runs_build_up_to_the_requested_one() {
+		let request = request(3, 1);
+		let runs = request.runs();
+		assert_eq!(runs.iter().map(|run| run.vegetables).collect::<Vec<_>>(), [1, 2, 3]);
+		assert!(runs.last() == Some(&request.requested_run()));
+	}
+
+	#[test]
+	fn runs_skip_the_build_up_without_candidates() {
+		let mut request = request(3, 1);
+		request.candidates = false;
+		assert!(request.runs() == [request.requested_run()]);
+		request.alternatives = 3;
+		assert_eq!(request.runs().iter().map(|run| run.vegetables).collect::<Vec<_>>(), [3, 3, 2, 2, 1, 1]);
+	}
+
+	#[test]
+	fn alternatives_add_nearby_runs_once_each() {
+		let runs = request(4, 3).runs();
+		// Complex processing is off already, so only the other cereal choice adds runs, for 2 to 4 vegetables.
+		assert_eq!(runs.len(), 4 + 3);
+		for (index, run) in runs.iter().enumerate() {
+			assert!(!runs.iter().skip(index + 1).any(|other| other == run), "run repeated");
+		}
+		assert!(runs.iter().any(|run| run.vegetables == 4 && !run.full_cereals));
+	}
+
+	#[test]
+	fn exclusions_add_nearby_runs() {
+		let mut request = request(4, 1);
+		request.exclusions.processings.insert("Fried".to_owned());
+		assert_eq!(request.runs().len(), 4 + 3);
+	}
+
+	/// What a solver gives for a maximum if, of recipes using `found` or `ruled_out` fillers, it
+	/// always picks the one using the most the maximum allows.
+	fn solver(max_fillers: u64, found: &[u64], ruled_out: &[u64]) -> Outcome {
+		let fillers = |count| fixtures::recipe(&[], 0, count, 0);
+		let best = found.iter().chain(ruled_out).copied().filter(|used| *used <= max_fillers).max();
+		match best {
+			Some(used) if found.contains(&used) => Outcome::Found(fillers(used)),
+			Some(used) => Outcome::RuledOut(fillers(used)),
+			None => Outcome::Nothing,
+		}
+	}
+
+	#[test]
+	fn fewest_fillers_bisects_without_filters() {
+		let mut tries = 0;
+		let fewest = fewest_fillers(10, false, |max_fillers| {
+			tries += 1;
+			solver(max_fillers, &[37], &[])
+		});
+		assert_eq!(fewest, Some(37));
+		assert!(tries <= 8, "{tries} tries");
+		assert_eq!(fewest_fillers(10, false, |_| Outcome::Nothing), None);
+		assert_eq!(fewest_fillers(MAX_FILLERS, false, |max_fillers| solver(max_fillers, &[0], &[])), None);
+	}
+
+	#[test]
+	fn fewest_fillers_skips_counts_giving_the_same_recipe_with_filters() {
+		// More fillers can lead to a recipe the inventory rules out, so what works isn't a threshold.
+		let mut tries = 0;
+		let fewest = fewest_fillers(10, true, |max_fillers| {
+			tries += 1;
+			solver(max_fillers, &[20, 50], &[12, 30])
+		});
+		assert_eq!(fewest, Some(20));
+		// 80, 49, 29, 19 and 11, which finds nothing.
+		assert_eq!(tries, 5);
+		// Bisecting would miss the recipe with 20.
+		assert_eq!(fewest_fillers(10, false, |max_fillers| solver(max_fillers, &[20, 50], &[12, 30])), Some(50));
+		assert_eq!(fewest_fillers(10, true, |max_fillers| solver(max_fillers, &[], &[40])), None);
+	}
+
+	#[test]
+	fn fewest_fillers_gives_up_after_a_few_tries_with_filters() {
+		let mut tries = 0;
+		let every_other: Vec<u64> = (11..=MAX_FILLERS).step_by(2).collect();
+		let fewest = fewest_fillers(10, true, |max_fillers| {
+			tries += 1;
+			solver(max_fillers, &every_other, &(12..=MAX_FILLERS).step_by(2).collect::<Vec<_>>())
+		});
+		assert_eq!(tries, FILTERED_FILLER_TRIES);
+		assert!(fewest.is_some_and(|fewest| fewest > 11));
+	}
+
+	#[test]
+	fn alternatives_are_ranked_and_distinct() {
+		let requested = request(3, 1).requested_run();
+		let short = fixtures::recipe(&[(0, 0), (1, 0), (2, 0)], 1, 2, 0);
+		let long = fixtures::recipe(&[(0, 0), (1, 0), (2, 1)], 1, 9, 0);
+		let fewer = fixtures::recipe(&[(0, 0), (1, 0)], 1, 0, 0);
+		let found = vec![
+			alternative(2, true, fewer.clone()),
+			alternative(3, true, long.clone()),
+			alternative(3, false, short.clone()),
+			alternative(3, true, short.clone()),
+		];
+
+		let ranked = rank_alternatives(found.clone(), &requested, 10);
+		let summary: Vec<(u64, u64)> = ranked.iter().map(|alternative| (alternative.run.vegetables, recipe_length(&alternative.recipe))).collect();
+		assert_eq!(summary, [(3, recipe_length(&short)), (3, recipe_length(&long)), (2, recipe_length(&fewer))]);
+		// Of two runs giving the same recipe, the requested one is kept.
+		assert!(ranked.first().map(|alternative| &alternative.run) == Some(&requested));
+
+		assert_eq!(rank_alternatives(found, &requested, 2).len(), 2);
+	}
+
+	#[test]
+	fn eta_follows_run_times_by_vegetable_count() {
+		let runs = request(3, 1).runs();
+		let mut tracker = ProgressTracker::new(0.0, runs);
+		let progress = |run| Progress {
+			run,
+			runs: 3,
+			vegetables: run as u64 + 1,
+			best_length: None,
+			diagnosing: false,
+		};
+		tracker.record(progress(0), 0.0);
+		assert_eq!(tracker.eta(0.0), None);
+		assert_eq!(tracker.fraction(0.0), 0.0);
+		tracker.record(progress(1), 1.0);
+		tracker.record(progress(2), 3.0);
+		// Runs went from 1 to 2 seconds, so the last one, not yet timed, is expected to take 4.
+		assert_eq!(tracker.eta(3.0), Some(4.0));
+		assert_eq!(tracker.eta(5.0), Some(2.0));
+		assert_eq!(tracker.eta(10.0), Some(0.0));
+		assert_eq!(tracker.elapsed(5.0), 5.0);
+	}
+}